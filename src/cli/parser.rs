@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::{path::PathBuf, time::Duration};
 
 use anyhow::{bail, Result};
 use either::Either;
@@ -6,17 +6,134 @@ use metron::{Header, Rate};
 use url::Url;
 use Either::{Left, Right};
 
-pub type RateArgValue = Either<Rate, (Rate, Rate)>;
+use crate::profile::{Arrival, Proxy, ResolveOverride, Template, TemplateContext, ThinkTime};
+
+pub type UsersArgValue = Either<usize, (usize, usize)>;
+
+/// Value of a `--rate` argument, which describes one segment of the plan.
+#[derive(Clone, Debug)]
+pub enum RateArgValue {
+    Fixed(Rate),
+    Linear(Rate, Rate),
+    Step(Rate, Rate, u32),
+    Sine(Rate, Rate, Duration),
+    Exponential(Rate, Rate),
+    Pause,
+    Trace(PathBuf),
+}
 
 /// Request rate clap [`Arg::value_parser`][clap::Arg::value_parser].
 pub fn rate(value: &str) -> Result<RateArgValue> {
-    if let Some((rate_start, rate_end)) = value.split_once(':') {
+    if value == "pause" {
+        Ok(RateArgValue::Pause)
+    } else if let Some(params) = value.strip_prefix("step:") {
+        let [rate_start, rate_end, steps] = split_params(params)?;
+        let rate_start = rate_start.parse()?;
+        let rate_end = rate_end.parse()?;
+        let steps = steps.parse()?;
+        if steps == 0 {
+            bail!("Number of steps cannot be zero");
+        }
+
+        Ok(RateArgValue::Step(rate_start, rate_end, steps))
+    } else if let Some(params) = value.strip_prefix("sine:") {
+        let [rate, amplitude, period] = split_params(params)?;
+        let rate = rate.parse()?;
+        let amplitude = amplitude.parse()?;
+        let period = period.parse::<humantime::Duration>()?;
+        Ok(RateArgValue::Sine(rate, amplitude, period.into()))
+    } else if let Some(path) = value.strip_prefix("trace:") {
+        if path.is_empty() {
+            bail!("Trace file path cannot be empty");
+        }
+
+        Ok(RateArgValue::Trace(path.into()))
+    } else if let Some(params) = value.strip_prefix("exp:") {
+        let [rate_start, rate_end] = split_params(params)?;
+        let rate_start = rate_start.parse()?;
+        let rate_end = rate_end.parse()?;
+        Ok(RateArgValue::Exponential(rate_start, rate_end))
+    } else if let Some((rate_start, rate_end)) = value.split_once(':') {
         let rate_start = rate_start.parse()?;
         let rate_end = rate_end.parse()?;
-        Ok(Right((rate_start, rate_end)))
+        Ok(RateArgValue::Linear(rate_start, rate_end))
     } else {
         let rate = value.parse()?;
-        Ok(Left(rate))
+        Ok(RateArgValue::Fixed(rate))
+    }
+}
+
+/// Splits the colon separated parameters of an argument value, which must have exactly `N`
+/// parameters.
+fn split_params<const N: usize>(params: &str) -> Result<[&str; N]> {
+    let params = params.split(':').collect::<Vec<_>>();
+    match params.try_into() {
+        Ok(params) => Ok(params),
+        Err(_) => bail!("Expected {} colon separated values", N),
+    }
+}
+
+/// Arrival process clap [`Arg::value_parser`][clap::Arg::value_parser].
+pub fn arrival(value: &str) -> Result<Arrival> {
+    let arrival = if let Some(jitter) = value.strip_prefix("uniform:") {
+        Arrival::Uniform {
+            jitter: jitter.parse()?,
+        }
+    } else if let Some(shape) = value.strip_prefix("pareto:") {
+        Arrival::Pareto {
+            shape: shape.parse()?,
+        }
+    } else {
+        match value {
+            "constant" => Arrival::Constant,
+            "poisson" => Arrival::Poisson,
+            _ => bail!("Unknown arrival process: {}", value),
+        }
+    };
+
+    arrival.validate()?;
+    Ok(arrival)
+}
+
+/// Trace scale clap [`Arg::value_parser`][clap::Arg::value_parser].
+pub fn scale(value: &str) -> Result<f64> {
+    let scale: f64 = value.strip_suffix('x').unwrap_or(value).parse()?;
+    if !scale.is_finite() || scale <= 0.0 {
+        bail!("Scale must be greater than zero");
+    }
+
+    Ok(scale)
+}
+
+/// Virtual users clap [`Arg::value_parser`][clap::Arg::value_parser].
+pub fn users(value: &str) -> Result<UsersArgValue> {
+    if let Some((users_start, users_end)) = value.split_once(':') {
+        let users_start = users_start.parse()?;
+        let users_end = users_end.parse()?;
+        Ok(Right((users_start, users_end)))
+    } else {
+        let users = value.parse()?;
+        Ok(Left(users))
+    }
+}
+
+/// Think time clap [`Arg::value_parser`][clap::Arg::value_parser].
+pub fn think_time(value: &str) -> Result<ThinkTime> {
+    let parse = |s: &str| s.parse::<humantime::Duration>().map(Duration::from);
+
+    if let Some(mean) = value.strip_prefix("exp:") {
+        Ok(ThinkTime::Exponential { mean: parse(mean)? })
+    } else if let Some((min, max)) = value.split_once(':') {
+        let (min, max) = (parse(min)?, parse(max)?);
+        if min > max {
+            bail!("Minimum think time cannot exceed the maximum");
+        }
+
+        Ok(ThinkTime::Uniform { min, max })
+    } else {
+        Ok(ThinkTime::Fixed {
+            duration: parse(value)?,
+        })
     }
 }
 
@@ -30,25 +147,69 @@ pub fn duration(value: &str) -> Result<Option<Duration>> {
     }
 }
 
+/// Timeout clap [`Arg::value_parser`][clap::Arg::value_parser].
+pub fn timeout(value: &str) -> Result<Duration> {
+    let timeout: Duration = value.parse::<humantime::Duration>()?.into();
+    if timeout.is_zero() {
+        bail!("Timeout cannot be zero");
+    }
+
+    Ok(timeout)
+}
+
 /// Target URL clap [`Arg::value_parser`][clap::Arg::value_parser].
-pub fn target(value: &str) -> Result<Url> {
-    let url = value.parse::<url::Url>()?;
+pub fn target(value: &str) -> Result<Template> {
+    let template = value.parse::<Template>()?;
+
+    // Validate the URL that results from rendering any placeholders. URLs that reference
+    // variables can only be validated once the variables are known.
+    if template.variables().next().is_some() {
+        return Ok(template);
+    }
+
+    let mut ctx = TemplateContext {
+        seq: 0,
+        rng: &mut rand::thread_rng(),
+        vars: None,
+    };
+    let url = template.render(&mut ctx)?.parse::<Url>()?;
 
     if url.cannot_be_a_base() {
         bail!("Supplied URL cannot be a base URL");
     }
 
     let scheme = url.scheme();
-    if scheme != "http" && scheme != "https" {
-        bail!("Only HTTP and HTTPS URL schemes are currently supported");
+    if scheme != "http" && scheme != "https" && scheme != "unix" {
+        bail!("Only HTTP, HTTPS and Unix socket URL schemes are currently supported");
     }
 
-    Ok(url)
+    Ok(template)
+}
+
+/// Template clap [`Arg::value_parser`][clap::Arg::value_parser].
+pub fn template(value: &str) -> Result<Template> {
+    value.parse()
+}
+
+/// Resolve override clap [`Arg::value_parser`][clap::Arg::value_parser].
+pub fn resolve(value: &str) -> Result<ResolveOverride> {
+    value.parse()
+}
+
+/// Proxy clap [`Arg::value_parser`][clap::Arg::value_parser].
+pub fn proxy(value: &str) -> Result<Proxy> {
+    value.parse()
 }
 
 /// Header clap [`Arg::value_parser`][clap::Arg::value_parser].
 pub fn header(value: &str) -> Result<Header> {
     if let Some((k, v)) = value.split_once(':') {
+        let (k, v) = (k.trim(), v.trim());
+        k.parse::<hyper::header::HeaderName>()?;
+        if v.parse::<Template>()?.is_static() {
+            v.parse::<hyper::header::HeaderValue>()?;
+        }
+
         Ok(Header {
             name: k.to_owned(),
             value: v.to_owned(),
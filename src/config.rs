@@ -7,7 +7,7 @@ pub enum Config {
     Operator(crate::operator::Config),
     Echo(crate::echo::Config),
     Node(crate::node::Config),
-    Profile(crate::profile::Config),
+    Profile(Box<crate::profile::Config>),
     Control(crate::control::Config),
 }
 
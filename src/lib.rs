@@ -1,30 +1,55 @@
-use std::{ops::Deref, str::FromStr, time::Duration};
+use std::{str::FromStr, time::Duration};
 
 use anyhow::bail;
 use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Deserialize, Serialize)]
-#[serde(try_from = "String")]
-pub struct Rate(u32);
+/// Request rate, expressed as a number of requests sent per period of time.
+///
+/// Rates are written as a (possibly fractional) number of requests per second (e.g. `100`
+/// or `0.5`) or as a number of requests per period (e.g. `30/m`, `2.5/s` or `1/5s`), where
+/// the period is a duration such as `5s` or a unit such as `m`.
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "RateValue", into = "String")]
+pub struct Rate {
+    /// Number of requests sent per period.
+    requests: f64,
+    /// Period that the requests are sent over.
+    per: Duration,
+}
 
 impl Rate {
+    /// Returns the interval between requests sent at this rate.
     pub fn as_interval(&self) -> Duration {
-        Duration::from_secs(1) / self.0
+        // Divide the period exactly where the number of requests is a whole number so that
+        // e.g. 3 RPS is scheduled every 333,333,333ns without accumulating rounding errors.
+        if self.requests.fract() == 0.0 && self.requests <= u32::MAX as f64 {
+            self.per / self.requests as u32
+        } else {
+            self.per.div_f64(self.requests)
+        }
+    }
+
+    /// Returns the number of requests per second.
+    pub fn per_sec(&self) -> f64 {
+        self.requests / self.per.as_secs_f64()
     }
 }
 
 impl std::fmt::Debug for Rate {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.0.fmt(f)
+        std::fmt::Display::fmt(self, f)
     }
 }
 
-impl Deref for Rate {
-    type Target = u32;
-
-    fn deref(&self) -> &Self::Target {
-        &self.0
+impl std::fmt::Display for Rate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.per == Duration::from_secs(1) {
+            write!(f, "{}", self.requests)
+        } else {
+            let per = humantime::format_duration(self.per);
+            write!(f, "{}/{}", self.requests, per)
+        }
     }
 }
 
@@ -32,7 +57,35 @@ impl FromStr for Rate {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
-        Rate::try_from(s.to_owned())
+        let (requests, per) = match s.split_once('/') {
+            Some((requests, per)) => (requests, per.trim()),
+            None => (s, "s"),
+        };
+
+        let requests = requests.trim().parse::<f64>()?;
+        if !requests.is_finite() || requests < 0.0 {
+            bail!("Request rate must be a positive number: {}", s);
+        }
+
+        if requests == 0.0 {
+            bail!("Request rate cannot be zero (use a pause segment instead)");
+        }
+
+        // A bare unit (e.g. "m") is a period of one of that unit.
+        let per = if per.starts_with(|c: char| c.is_ascii_digit()) {
+            per.parse::<humantime::Duration>()?
+        } else {
+            format!("1{}", per).parse::<humantime::Duration>()?
+        };
+
+        if per.is_zero() {
+            bail!("Request rate period cannot be zero: {}", s);
+        }
+
+        Ok(Rate {
+            requests,
+            per: per.into(),
+        })
     }
 }
 
@@ -43,12 +96,33 @@ impl TryFrom<String> for Rate {
     type Error = anyhow::Error;
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
-        let rate = value.parse()?;
-        if rate == 0 {
-            bail!("Request rate cannot be zero");
+        value.parse()
+    }
+}
+
+/// Rate as written in a config file, where a number of requests per second may be written
+/// as a plain number rather than a string.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RateValue {
+    Number(f64),
+    String(String),
+}
+
+impl TryFrom<RateValue> for Rate {
+    type Error = anyhow::Error;
+
+    fn try_from(value: RateValue) -> Result<Self, Self::Error> {
+        match value {
+            RateValue::Number(rate) => rate.to_string().parse(),
+            RateValue::String(rate) => rate.parse(),
         }
+    }
+}
 
-        Ok(Rate(rate))
+impl From<Rate> for String {
+    fn from(rate: Rate) -> Self {
+        rate.to_string()
     }
 }
 
@@ -121,3 +195,67 @@ impl Default for HttpMethod {
         Self::Get
     }
 }
+
+/// HTTP protocol used to send requests.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum HttpProtocol {
+    /// HTTP/1.1 only.
+    #[default]
+    Http1,
+    /// HTTP/2 where it can be negotiated using TLS ALPN, otherwise HTTP/1.1.
+    Http2,
+    /// HTTP/2 without negotiation (i.e. "prior knowledge"); this allows cleartext HTTP/2.
+    Http2PriorKnowledge,
+}
+
+/// How response bodies are read.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ResponseBody {
+    /// Latency is measured until the response headers arrive. The body is then read and
+    /// discarded so that the connection can be reused.
+    #[default]
+    Headers,
+    /// Latency is measured until the whole body has been read. The body is discarded as it
+    /// streams in.
+    Discard,
+    /// Latency is measured until the whole body has been read. The body is buffered in
+    /// memory as it streams in.
+    Buffer,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rates() {
+        let interval = |rate: &str| rate.parse::<Rate>().unwrap().as_interval();
+        assert_eq!(interval("100"), Duration::from_millis(10));
+        assert_eq!(interval("0.5"), Duration::from_secs(2));
+        assert_eq!(interval("30/m"), Duration::from_secs(2));
+        assert_eq!(interval("2.5/s"), Duration::from_millis(400));
+        assert_eq!(interval("1/5s"), Duration::from_secs(5));
+        assert_eq!(interval("3"), Duration::from_nanos(333_333_333));
+
+        let rate = "1/5s".parse::<Rate>().unwrap();
+        assert_eq!(rate.per_sec(), 0.2);
+        assert_eq!(rate.to_string(), "1/5s");
+        assert_eq!(rate.to_string().parse::<Rate>().unwrap(), rate);
+
+        for rate in ["0", "-1", "x", "1/0s", "1/x", "NaN", "inf"] {
+            assert!(rate.parse::<Rate>().is_err(), "{}", rate);
+        }
+    }
+
+    #[test]
+    fn rates_in_yaml() {
+        let rates: Vec<Rate> = serde_yaml::from_str("[100, 0.5, 30/m, '1/5s']").unwrap();
+        assert_eq!(rates[0].per_sec(), 100.0);
+        assert_eq!(rates[1].per_sec(), 0.5);
+        assert_eq!(rates[2].per_sec(), 0.5);
+        assert_eq!(rates[3].per_sec(), 0.2);
+        assert_eq!(serde_yaml::to_string(&rates[2]).unwrap(), "30/1m\n");
+    }
+}
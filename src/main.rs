@@ -10,10 +10,13 @@ mod control;
 mod runtime;
 mod wait;
 
-use std::env;
+use std::{env, process, sync::Arc};
 
 use anyhow::{Context, Result};
 use config::Config;
+use log::warn;
+use serde::Serialize;
+use tokio::signal::unix::{signal, SignalKind};
 
 use crate::profile::Profiler;
 
@@ -61,8 +64,43 @@ async fn run_node(_config: &node::Config) -> Result<()> {
 }
 
 async fn run_profile_test(config: &profile::Config) -> Result<()> {
-    let profiler = Profiler::new(config.clone());
-    let report = profiler.run().await;
+    let profiler = Arc::new(Profiler::new(config.clone()));
+    let mut run = tokio::spawn({
+        let profiler = profiler.clone();
+        async move { profiler.run().await }
+    });
+
+    let mut interim = signal(SignalKind::user_defined1())?;
+    let interrupt = interrupted();
+    tokio::pin!(interrupt);
+    let mut stopping = false;
+
+    let report = loop {
+        tokio::select! {
+            report = &mut run => break report?,
+            // Print an interim report without stopping the test.
+            _ = interim.recv() => {
+                if let Some(report) = profiler.interim_report().await {
+                    print_report(&report)?;
+                }
+            }
+            // The first interrupt stops the test gracefully so that a report is still printed
+            // whereas a second interrupt exits immediately.
+            result = &mut interrupt, if !stopping => {
+                result?;
+                warn!("Interrupted; waiting for in-flight requests (interrupt again to exit)");
+                profiler.stop();
+                stopping = true;
+
+                tokio::spawn(async {
+                    if interrupted().await.is_ok() {
+                        process::exit(130);
+                    }
+                });
+            }
+        }
+    };
+
     match report {
         Ok(ref report) => print_report(report)?,
         Err(ref err) => {
@@ -82,7 +120,18 @@ async fn run_control_command(_config: &control::Config) -> Result<()> {
     Ok(())
 }
 
-fn print_report(report: &profile::Report) -> Result<()> {
+/// Waits until the process receives SIGINT (e.g. CTRL-C) or SIGTERM.
+async fn interrupted() -> Result<()> {
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        interrupt = tokio::signal::ctrl_c() => interrupt?,
+        _ = terminate.recv() => {}
+    }
+
+    Ok(())
+}
+
+fn print_report<T: Serialize>(report: &T) -> Result<()> {
     println!("{}", serde_yaml::to_string(report)?);
     Ok(())
 }
@@ -0,0 +1,83 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::scenario::{serde_regex, JsonPath};
+
+/// Check that is applied to each response to a request.
+///
+/// A response that fails an assertion is still reported under its HTTP status, but the
+/// failure is also counted against the assertion's name in the report.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Assertion {
+    /// Name used to identify the assertion in the report.
+    pub name: String,
+    /// Check that the response must pass.
+    #[serde(flatten)]
+    pub check: Check,
+}
+
+/// Check that a response must pass.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Check {
+    /// The HTTP status must be one of the listed statuses.
+    Status(Vec<u16>),
+    /// The response body must contain the string.
+    BodyContains(String),
+    /// The response body must match the regular expression.
+    BodyRegex(#[serde(with = "serde_regex")] Regex),
+    /// The value at the JSON path into the response body must equal the expected value.
+    Json {
+        path: JsonPath,
+        equals: serde_json::Value,
+    },
+    /// The response body must not exceed the number of bytes.
+    MaxBodySize(usize),
+}
+
+impl Assertion {
+    /// Returns whether the response passes the assertion.
+    pub fn passes(&self, status: u16, body: &[u8]) -> bool {
+        match &self.check {
+            Check::Status(statuses) => statuses.contains(&status),
+            Check::BodyContains(s) => String::from_utf8_lossy(body).contains(s.as_str()),
+            Check::BodyRegex(re) => re.is_match(&String::from_utf8_lossy(body)),
+            Check::Json { path, equals } => serde_json::from_slice(body)
+                .ok()
+                .and_then(|value| path.find(&value).ok().map(|v| v == equals))
+                .unwrap_or(false),
+            Check::MaxBodySize(max) => body.len() <= *max,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn passes(yaml: &str, status: u16, body: &str) -> bool {
+        let assertion: Assertion = serde_yaml::from_str(yaml).unwrap();
+        assertion.passes(status, body.as_bytes())
+    }
+
+    #[test]
+    fn checks() {
+        let body = r#"{"status": "ok", "count": 2}"#;
+
+        assert!(passes("{name: a, status: [200, 201]}", 201, body));
+        assert!(!passes("{name: a, status: [200, 201]}", 500, body));
+        assert!(passes("{name: a, body_contains: '\"ok\"'}", 200, body));
+        assert!(!passes("{name: a, body_contains: error}", 200, body));
+        assert!(passes(r"{name: a, body_regex: 'count.: \d'}", 200, body));
+
+        let status_ok = "{name: a, json: {path: $.status, equals: ok}}";
+        assert!(passes(status_ok, 200, body));
+        assert!(!passes(status_ok, 200, "error"));
+        let count_2 = "{name: a, json: {path: $.count, equals: 2}}";
+        assert!(passes(count_2, 200, body));
+        assert!(!passes(&count_2.replace('2', "'2'"), 200, body));
+
+        assert!(passes("{name: a, max_body_size: 28}", 200, body));
+        assert!(!passes("{name: a, max_body_size: 27}", 200, body));
+    }
+}
@@ -0,0 +1,327 @@
+use std::{
+    error::Error as StdError,
+    io,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use anyhow::Context;
+use hyper::{Body, Request, Response, Version};
+use metron::HttpProtocol;
+use serde::Serialize;
+use thiserror::Error;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use super::{
+    connector::{Connector, ResolveError},
+    proxy, Config,
+};
+
+/// Idle timeout applied to pooled connections. Connections are expected to be reused for
+/// the duration of the test so this is set to be generous.
+const CONNECTION_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Default maximum number of concurrent HTTP/2 streams per connection.
+const DEFAULT_MAX_CONCURRENT_STREAMS: usize = 100;
+
+/// HTTP client that sends requests over a fixed set of persistent connections.
+///
+/// Each connection slot wraps its own `hyper::Client` that is only ever allowed a single
+/// outstanding request. As an HTTP/1.1 connection can only carry one request at a time, this
+/// means that each slot holds (at most) one persistent connection per target host and the
+/// total number of connections per host is bounded by the number of slots.
+///
+/// When HTTP/2 is used, each slot is allowed as many outstanding requests as the configured
+/// maximum number of concurrent streams, which are multiplexed over the slot's connection.
+/// If HTTP/2 is only offered (rather than assumed with prior knowledge), a slot is limited to
+/// a single outstanding request until a response confirms that the target negotiated HTTP/2,
+/// so a target that only supports HTTP/1.1 still gets one connection per slot.
+///
+/// Requests are spread across the connection slots using round-robin.
+#[derive(Clone)]
+pub struct Client {
+    /// Connection slots.
+    connections: Arc<Vec<Connection>>,
+    /// Index of the next connection slot to be used.
+    next: Arc<AtomicUsize>,
+}
+
+#[derive(Clone)]
+struct Connection {
+    /// Underlying client that manages the connection.
+    client: hyper::Client<Connector>,
+    /// Permits that limit the number of outstanding requests on the connection.
+    permits: Arc<Semaphore>,
+    /// Permits to be added once the connection is known to use HTTP/2.
+    pending_streams: Arc<AtomicUsize>,
+}
+
+impl Client {
+    /// Creates a new `Client` based on the connection settings in the profile config.
+    pub fn new(config: &Config) -> anyhow::Result<Self> {
+        let offer_h2 = config.http_protocol != HttpProtocol::Http1;
+        let max_streams = config
+            .max_concurrent_streams
+            .unwrap_or(DEFAULT_MAX_CONCURRENT_STREAMS);
+        let (streams, pending_streams) = match config.http_protocol {
+            HttpProtocol::Http1 => (1, 0),
+            HttpProtocol::Http2 => (1, max_streams.saturating_sub(1)),
+            HttpProtocol::Http2PriorKnowledge => (max_streams, 0),
+        };
+
+        // The TLS connector reads the certificate files so it is only built once and shared
+        // by every connection.
+        let connector = Connector::new(
+            config.connect_timeout,
+            offer_h2,
+            &config.tls,
+            &config.resolve,
+            config.proxy.as_ref(),
+        )
+        .context("Could not create TLS connector")?;
+
+        let connections = (0..config.connections.max(1))
+            .map(|slot| {
+                let connector = if config.spread_addresses {
+                    connector.with_spread(slot)
+                } else {
+                    connector.clone()
+                };
+
+                let client = hyper::Client::builder()
+                    .pool_max_idle_per_host(1)
+                    .pool_idle_timeout(CONNECTION_IDLE_TIMEOUT)
+                    .http2_only(config.http_protocol == HttpProtocol::Http2PriorKnowledge)
+                    .build(connector);
+
+                Connection {
+                    client,
+                    permits: Arc::new(Semaphore::new(streams)),
+                    pending_streams: Arc::new(AtomicUsize::new(pending_streams)),
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            connections: Arc::new(connections),
+            next: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    /// Acquires the next connection.
+    ///
+    /// The returned future completes once the connection is free to send a request.
+    pub async fn acquire(&self) -> Lease {
+        let id = self.next.fetch_add(1, Ordering::Relaxed) % self.connections.len();
+        let conn = self.connections[id].clone();
+
+        // Safe to unwrap since we never close the semaphore.
+        let permit = conn.permits.clone().acquire_owned().await.unwrap();
+
+        Lease {
+            id,
+            conn,
+            _permit: permit,
+        }
+    }
+}
+
+/// Exclusive use of a connection for the purposes of sending a single request.
+pub struct Lease {
+    /// Identifies the leased connection.
+    id: usize,
+    /// Leased connection slot.
+    conn: Connection,
+    /// Permit that is released when the lease is dropped.
+    _permit: OwnedSemaphorePermit,
+}
+
+impl Lease {
+    /// Returns the ID of the leased connection.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Sends the request over the leased connection.
+    ///
+    /// The lease should be held until the response body has been consumed so that the
+    /// connection can be returned to the pool before the next request is sent on it.
+    pub async fn request(&self, req: Request<Body>) -> hyper::Result<Response<Body>> {
+        let res = self.conn.client.request(req).await?;
+
+        // Allow concurrent requests on the connection once it has negotiated HTTP/2.
+        if res.version() == Version::HTTP_2 {
+            let pending = self.conn.pending_streams.swap(0, Ordering::Relaxed);
+            if pending > 0 {
+                self.conn.permits.add_permits(pending);
+            }
+        }
+
+        Ok(res)
+    }
+}
+
+/// Client-side error encountered while sending a request or receiving its response.
+#[derive(Error, Clone, Debug)]
+#[error("{message}")]
+pub struct ClientError {
+    /// Classification of the error.
+    pub kind: ClientErrorKind,
+    /// Description of the error including its underlying causes.
+    pub message: String,
+}
+
+/// Classification of client-side errors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientErrorKind {
+    /// The target host name could not be resolved.
+    Dns,
+    /// The target refused the connection.
+    ConnectionRefused,
+    /// The connection was reset or closed by the target.
+    ConnectionReset,
+    /// The TLS handshake failed.
+    Tls,
+    /// The proxy refused or failed to establish a tunnel to the target.
+    Proxy,
+    /// The request or connection attempt timed out.
+    Timeout,
+    /// The target responded in a way that violates the HTTP protocol.
+    Protocol,
+    /// The response body could not be read.
+    BodyRead,
+    /// The request could not be built (e.g. because a placeholder rendered an invalid URL)
+    /// and so was not sent.
+    InvalidRequest,
+    /// A value could not be extracted from the response to a scenario step.
+    Extraction,
+    /// The error could not be classified.
+    Other,
+}
+
+impl ClientError {
+    /// Creates an error for a request that did not complete within `timeout`.
+    pub fn timeout(timeout: Duration) -> Self {
+        Self {
+            kind: ClientErrorKind::Timeout,
+            message: format!(
+                "request timed out after {}",
+                humantime::format_duration(timeout)
+            ),
+        }
+    }
+
+    /// Creates an error for a response body that could not be read.
+    pub fn body_read(err: &hyper::Error) -> Self {
+        Self {
+            kind: ClientErrorKind::BodyRead,
+            message: describe(err),
+        }
+    }
+
+    /// Creates an error for a request that could not be built.
+    pub fn invalid_request(err: &anyhow::Error) -> Self {
+        Self {
+            kind: ClientErrorKind::InvalidRequest,
+            message: describe(err.as_ref()),
+        }
+    }
+
+    /// Creates an error for a value that could not be extracted from a response.
+    pub fn extraction(var: &str, err: &anyhow::Error) -> Self {
+        Self {
+            kind: ClientErrorKind::Extraction,
+            message: format!("could not extract {}: {}", var, describe(err.as_ref())),
+        }
+    }
+}
+
+impl From<hyper::Error> for ClientError {
+    fn from(err: hyper::Error) -> Self {
+        Self {
+            kind: classify(&err),
+            message: describe(&err),
+        }
+    }
+}
+
+/// Classifies the error by inspecting it and its chain of underlying causes.
+fn classify(err: &hyper::Error) -> ClientErrorKind {
+    if err.is_timeout() {
+        return ClientErrorKind::Timeout;
+    }
+
+    let mut source = err.source();
+    while let Some(err) = source {
+        if err.is::<native_tls::Error>() {
+            return ClientErrorKind::Tls;
+        }
+
+        if err.is::<ResolveError>() {
+            return ClientErrorKind::Dns;
+        }
+
+        if let Some(err) = err.downcast_ref::<h2::Error>() {
+            return err
+                .get_io()
+                .and_then(classify_io)
+                .unwrap_or(ClientErrorKind::Protocol);
+        }
+
+        if let Some(err) = err.downcast_ref::<io::Error>() {
+            // The proxy's errors are wrapped by the I/O error rather than being its source.
+            if err.get_ref().is_some_and(|err| err.is::<proxy::Error>()) {
+                return ClientErrorKind::Proxy;
+            }
+
+            if let Some(kind) = classify_io(err) {
+                return kind;
+            }
+        }
+
+        source = err.source();
+    }
+
+    if err.is_incomplete_message() || err.is_closed() || err.is_canceled() {
+        ClientErrorKind::ConnectionReset
+    } else if err.is_parse() || err.is_parse_status() {
+        ClientErrorKind::Protocol
+    } else {
+        ClientErrorKind::Other
+    }
+}
+
+/// Returns the kind of the I/O error (if it has a more specific kind than `Other`).
+fn classify_io(err: &io::Error) -> Option<ClientErrorKind> {
+    match err.kind() {
+        io::ErrorKind::TimedOut => Some(ClientErrorKind::Timeout),
+        io::ErrorKind::ConnectionRefused => Some(ClientErrorKind::ConnectionRefused),
+        io::ErrorKind::ConnectionReset
+        | io::ErrorKind::ConnectionAborted
+        | io::ErrorKind::BrokenPipe
+        | io::ErrorKind::UnexpectedEof => Some(ClientErrorKind::ConnectionReset),
+        _ => None,
+    }
+}
+
+/// Describes the error along with its chain of underlying causes.
+fn describe(err: &dyn StdError) -> String {
+    let mut message = err.to_string();
+    let mut source = err.source();
+    while let Some(err) = source {
+        // Some errors already include their cause in their own description.
+        let cause = err.to_string();
+        if !message.contains(&cause) {
+            message.push_str(": ");
+            message.push_str(&cause);
+        }
+
+        source = err.source();
+    }
+
+    message
+}
@@ -1,24 +1,86 @@
-use metron::{Header, HttpMethod, LogLevel};
+use std::time::Duration;
+
+use metron::{Header, HttpMethod, HttpProtocol, LogLevel, ResponseBody};
 use serde::{Deserialize, Serialize};
-use url::Url;
 
 use crate::{
-    profile::{PlanSegment, SignallerKind},
+    profile::{
+        Arrival, Assertion, FeederConfig, PlanSegment, ProxyConfig, RequestTemplate,
+        ResolveOverride, Scenario, SignallerKind, Target, TargetSelection, Template, ThinkTime,
+        TlsConfig, UserSegment,
+    },
     runtime,
 };
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct Config {
+    #[serde(default)]
     pub segments: Vec<PlanSegment>,
+    #[serde(default)]
+    pub arrival: Arrival,
+    #[serde(default)]
+    pub users: Vec<UserSegment>,
+    pub think_time: Option<ThinkTime>,
+    #[serde(default)]
     pub connections: usize,
+    pub max_in_flight: Option<usize>,
+    #[serde(default)]
+    #[serde(with = "humantime_serde")]
+    pub timeout: Option<Duration>,
+    #[serde(default)]
+    #[serde(with = "humantime_serde")]
+    pub connect_timeout: Option<Duration>,
+    #[serde(default = "default_drain_timeout")]
+    #[serde(with = "humantime_serde")]
+    pub drain_timeout: Option<Duration>,
+    #[serde(default)]
     pub http_method: HttpMethod,
-    pub targets: Vec<Url>,
+    #[serde(default)]
+    pub http_protocol: HttpProtocol,
+    pub max_concurrent_streams: Option<usize>,
+    #[serde(default)]
+    pub response_body: ResponseBody,
+    #[serde(default)]
+    pub tls: TlsConfig,
+    #[serde(default)]
+    pub resolve: Vec<ResolveOverride>,
+    #[serde(default)]
+    pub spread_addresses: bool,
+    #[serde(default)]
+    pub proxy: Option<ProxyConfig>,
+    #[serde(default)]
+    pub targets: Vec<Target>,
+    #[serde(default)]
+    pub requests: Vec<RequestTemplate>,
+    #[serde(default)]
+    pub scenario: Option<Scenario>,
+    #[serde(default)]
+    pub target_selection: TargetSelection,
+    pub seed: Option<u64>,
+    #[serde(default)]
     pub headers: Vec<Header>,
-    pub payload: Option<String>,
+    pub payload: Option<Template>,
+    #[serde(default)]
+    pub assertions: Vec<Assertion>,
+    #[serde(default)]
+    pub feeder: Option<FeederConfig>,
+    #[serde(default)]
     pub runtime: runtime::Config,
+    #[serde(default)]
     pub signaller_kind: SignallerKind,
+    #[serde(default)]
     pub no_latency_correction: bool,
+    #[serde(default)]
     pub stop_on_client_error: bool,
+    #[serde(default)]
     pub stop_on_non_2xx: bool,
+    #[serde(default)]
+    pub stop_on_assertion_failure: bool,
+    #[serde(default)]
     pub log_level: LogLevel,
 }
+
+/// Drain timeout of config files that do not set one, which matches `--drain-timeout`.
+fn default_drain_timeout() -> Option<Duration> {
+    Some(Duration::from_secs(5))
+}
@@ -0,0 +1,570 @@
+use std::{
+    ffi::OsString,
+    future::{self, Future},
+    io,
+    net::{IpAddr, SocketAddr},
+    os::unix::ffi::OsStringExt,
+    path::PathBuf,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use hyper::{
+    client::{
+        connect::{
+            dns::{GaiResolver, Name},
+            Connected, Connection,
+        },
+        HttpConnector,
+    },
+    service::Service,
+    Uri,
+};
+use thiserror::Error;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::{TcpStream, UnixStream},
+};
+use tokio_native_tls::{TlsConnector, TlsStream};
+
+use super::{ProxyConfig, ResolveOverride, TlsConfig};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// ALPN protocol identifier for HTTP/2.
+const ALPN_H2: &str = "h2";
+
+/// ALPN protocol identifier for HTTP/1.1.
+const ALPN_HTTP1: &str = "http/1.1";
+
+/// URI scheme of requests that are sent over Unix domain sockets.
+pub const UNIX_SCHEME: &str = "unix";
+
+/// Returns the URI of a request that is sent over the Unix domain socket at `socket`.
+///
+/// The socket path is hex encoded as the host of the URI so that it survives as the URI's
+/// authority, which identifies the connections that the request may be sent over.
+pub fn unix_uri(socket: &str, path_and_query: &str) -> anyhow::Result<Uri> {
+    if socket.is_empty() {
+        anyhow::bail!("Unix socket URL is missing the socket path");
+    }
+
+    let host = socket
+        .bytes()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+
+    Ok(Uri::builder()
+        .scheme(UNIX_SCHEME)
+        .authority(host)
+        .path_and_query(path_and_query)
+        .build()?)
+}
+
+/// Returns the path of the Unix domain socket that the request should be sent over (if the
+/// URI was created by [unix_uri]).
+fn socket_path(uri: &Uri) -> Option<PathBuf> {
+    if uri.scheme_str() != Some(UNIX_SCHEME) {
+        return None;
+    }
+
+    let host = uri.host()?.as_bytes();
+    let path = host
+        .chunks(2)
+        .map(|b| u8::from_str_radix(std::str::from_utf8(b).ok()?, 16).ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(OsString::from_vec(path).into())
+}
+
+/// Connector that establishes TCP connections and, for HTTPS targets, negotiates TLS.
+///
+/// Unlike `hyper_tls::HttpsConnector`, this connector negotiates the application protocol
+/// using ALPN so that HTTP/2 can be used over TLS where the target supports it.
+///
+/// The time taken by each phase of establishing a connection is recorded as
+/// [ConnectTimings], which are attached to the responses received over the connection.
+///
+/// Host names are resolved using the system resolver unless overridden. When connections
+/// are spread across addresses, each connector connects to a different one of the addresses
+/// that a host name resolves to (based on its connection slot).
+///
+/// If a proxy is configured, connections to targets that it applies to are tunnelled
+/// through the proxy before TLS is negotiated, even for HTTP targets. Unless the proxy
+/// resolves the host names of the targets itself, they are resolved (and spread) in the same
+/// way before being sent to the proxy.
+#[derive(Clone)]
+pub struct Connector {
+    /// Timeout applied when establishing the underlying TCP connections.
+    connect_timeout: Option<Duration>,
+    /// Connector used to negotiate TLS on top of the TCP connections.
+    tls: TlsConnector,
+    /// Server name used for TLS instead of the target's host name (if any).
+    server_name: Option<String>,
+    /// Addresses that host names resolve to in place of a DNS lookup.
+    overrides: Arc<[ResolveOverride]>,
+    /// Connection slot used to pick the address to connect to (if connections are spread
+    /// across addresses).
+    spread: Option<usize>,
+    /// Proxy that connections are tunnelled through (if any).
+    proxy: Option<Arc<ProxyConfig>>,
+}
+
+impl Connector {
+    /// Creates a new `Connector`.
+    ///
+    /// # Arguments
+    ///
+    /// * `connect_timeout` - Timeout applied when establishing TCP connections
+    /// * `offer_h2` - Whether HTTP/2 should be offered during ALPN negotiation
+    /// * `tls` - TLS configuration applied to connections to HTTPS targets
+    /// * `overrides` - Addresses that host names resolve to in place of a DNS lookup
+    /// * `proxy` - Proxy that connections are tunnelled through (if any)
+    pub fn new(
+        connect_timeout: Option<Duration>,
+        offer_h2: bool,
+        tls: &TlsConfig,
+        overrides: &[ResolveOverride],
+        proxy: Option<&ProxyConfig>,
+    ) -> anyhow::Result<Self> {
+        let alpn: &[&str] = if offer_h2 {
+            &[ALPN_H2, ALPN_HTTP1]
+        } else {
+            &[ALPN_HTTP1]
+        };
+
+        Ok(Self {
+            connect_timeout,
+            tls: tls.connector(alpn)?.into(),
+            server_name: tls.server_name.clone(),
+            overrides: overrides.into(),
+            spread: None,
+            proxy: proxy.cloned().map(Arc::new),
+        })
+    }
+
+    /// Returns a copy of the connector (sharing its TLS connector) that spreads connections
+    /// across addresses based on the connection slot.
+    pub fn with_spread(&self, slot: usize) -> Self {
+        Self {
+            spread: Some(slot),
+            ..self.clone()
+        }
+    }
+}
+
+/// Time taken by each phase of establishing a connection.
+#[derive(Clone, Debug)]
+pub struct ConnectTimings {
+    /// Time taken to resolve the host name (if it was not an IP address).
+    pub dns: Option<Duration>,
+    /// Time taken to establish the TCP connection (including the time taken to establish
+    /// a tunnel through the proxy if the connection was proxied).
+    pub connect: Duration,
+    /// Time taken by the TLS handshake (if the connection uses TLS).
+    pub tls: Option<Duration>,
+    /// When the connection was ready to send requests.
+    pub established: Instant,
+    /// Whether the timings have been claimed by a response.
+    claimed: Arc<AtomicBool>,
+}
+
+impl ConnectTimings {
+    /// Claims the timings on behalf of a response received over the connection.
+    ///
+    /// The timings are attached to every response received over the connection but only the
+    /// first response to claim them (i.e. the response to the request that had to wait for the
+    /// connection to be established) should account for them. Returns `None` if the timings
+    /// have already been claimed.
+    pub fn claim(&self) -> Option<&Self> {
+        (!self.claimed.swap(true, Ordering::Relaxed)).then_some(self)
+    }
+}
+
+/// Marker that is attached to the responses received over connections that were tunnelled
+/// through a proxy.
+#[derive(Clone, Copy, Debug)]
+pub struct Proxied;
+
+/// Error returned when a host name could not be resolved.
+#[derive(Debug, Error)]
+#[error("could not resolve {host}")]
+pub struct ResolveError {
+    host: String,
+    source: io::Error,
+}
+
+/// Resolver that records how long it takes to resolve each host name.
+///
+/// Overridden host names are not resolved. If connections are spread across addresses, the
+/// address picked for the connection slot is returned first.
+#[derive(Clone)]
+struct TimedResolver {
+    resolver: GaiResolver,
+    /// Addresses used in place of a DNS lookup (if the host name is overridden).
+    overrides: Option<Vec<IpAddr>>,
+    /// Connection slot used to pick the address to connect to (if any).
+    spread: Option<usize>,
+    /// Time taken by the most recent resolution.
+    elapsed: Arc<Mutex<Option<Duration>>>,
+}
+
+impl Service<Name> for TimedResolver {
+    type Response = std::vec::IntoIter<SocketAddr>;
+    type Error = ResolveError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // The system resolver is always ready as each lookup runs on a blocking thread.
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        // The connector sets the port of each address.
+        if let Some(addrs) = &self.overrides {
+            let addrs = addrs.iter().map(|&ip| SocketAddr::new(ip, 0)).collect();
+            let addrs = spread_addrs(addrs, self.spread);
+            return Box::pin(future::ready(Ok(addrs.into_iter())));
+        }
+
+        let start = Instant::now();
+        let host = name.as_str().to_owned();
+        let resolving = self.resolver.call(name);
+        let spread = self.spread;
+        let elapsed = self.elapsed.clone();
+
+        Box::pin(async move {
+            let addrs = resolving
+                .await
+                .map_err(|source| ResolveError { host, source })?
+                .collect();
+            *elapsed.lock().unwrap() = Some(start.elapsed());
+            Ok(spread_addrs(addrs, spread).into_iter())
+        })
+    }
+}
+
+/// Orders the addresses so that the address picked for the connection slot (if any) is
+/// tried first.
+///
+/// Addresses are sorted before picking so that each slot keeps the same address across
+/// lookups that return the addresses in different orders (e.g. round-robin DNS).
+fn spread_addrs(mut addrs: Vec<SocketAddr>, slot: Option<usize>) -> Vec<SocketAddr> {
+    if let Some(slot) = slot && !addrs.is_empty() {
+        addrs.sort();
+        addrs.dedup();
+
+        let len = addrs.len();
+        addrs.rotate_left(slot % len);
+    }
+
+    addrs
+}
+
+impl Service<Uri> for Connector {
+    type Response = Stream;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        if let Some(path) = socket_path(&dst) {
+            return Box::pin(connect_unix(path, self.connect_timeout));
+        }
+
+        let is_https = dst.scheme_str() == Some("https");
+        let port = dst.port_u16().unwrap_or(if is_https { 443 } else { 80 });
+        let target_host = dst
+            .host()
+            .unwrap_or_default()
+            .trim_matches(|c| c == '[' || c == ']')
+            .to_owned();
+        let host = self.server_name.clone().unwrap_or_else(|| target_host.clone());
+
+        // If the connection is proxied, we connect to the proxy rather than the target.
+        let proxy = self.proxy.clone().filter(|p| p.applies_to(&target_host));
+        let dst = match &proxy {
+            Some(proxy) => proxy.url.uri().clone(),
+            None => dst,
+        };
+
+        let overrides = dst.host().and_then(|host| {
+            let port = dst.port_u16().unwrap_or(if is_https { 443 } else { 80 });
+            ResolveOverride::find(&self.overrides, host, port).map(|addrs| addrs.to_vec())
+        });
+
+        // Each connection gets its own resolver so that the time taken to resolve the host
+        // name can be attributed to the connection.
+        let dns = Arc::new(Mutex::new(None));
+        let resolver = TimedResolver {
+            resolver: GaiResolver::new(),
+            overrides,
+            spread: self.spread,
+            elapsed: dns.clone(),
+        };
+
+        let mut http = HttpConnector::new_with_resolver(resolver);
+        http.enforce_http(false);
+        http.set_connect_timeout(self.connect_timeout);
+
+        let connecting = http.call(dst);
+        let tls = self.tls.clone();
+        let target_overrides = self.overrides.clone();
+        let spread = self.spread;
+
+        Box::pin(async move {
+            let start = Instant::now();
+            let mut tcp = connecting.await?;
+            let mut resolved = None;
+            if let Some(proxy) = &proxy {
+                let target = if proxy.url.resolves_hosts() || target_host.parse::<IpAddr>().is_ok()
+                {
+                    target_host
+                } else {
+                    let resolving = Instant::now();
+                    let addr = resolve_target(&target_host, port, &target_overrides, spread);
+                    let addr = addr.await?;
+                    resolved = Some(resolving.elapsed());
+                    addr.to_string()
+                };
+
+                proxy.url.tunnel(&mut tcp, &target, port).await?;
+            }
+
+            let connected = Instant::now();
+
+            // The TCP connect phase excludes the time taken to resolve the host names of both
+            // the proxy and the target (if either was resolved).
+            let dns = [*dns.lock().unwrap(), resolved];
+            let dns = dns.into_iter().flatten().reduce(|a, b| a + b);
+            let connect = (connected - start).saturating_sub(dns.unwrap_or_default());
+
+            let (io, tls) = if is_https {
+                let tls = tls.connect(&host, tcp).await?;
+                (Io::Https(Box::new(tls)), Some(connected.elapsed()))
+            } else {
+                (Io::Http(tcp), None)
+            };
+
+            let timings = ConnectTimings {
+                dns,
+                connect,
+                tls,
+                established: Instant::now(),
+                claimed: Arc::new(AtomicBool::new(false)),
+            };
+
+            Ok(Stream {
+                io,
+                timings,
+                proxied: proxy.is_some(),
+            })
+        })
+    }
+}
+
+/// Resolves the host name of a target for a proxy that is sent the target's address rather
+/// than its host name, picking the address for the connection slot (if connections are
+/// spread across addresses).
+async fn resolve_target(
+    host: &str,
+    port: u16,
+    overrides: &[ResolveOverride],
+    spread: Option<usize>,
+) -> Result<IpAddr, ResolveError> {
+    let error = |source| ResolveError {
+        host: host.to_owned(),
+        source,
+    };
+
+    let addrs = match ResolveOverride::find(overrides, host, port) {
+        Some(addrs) => addrs.iter().map(|&ip| SocketAddr::new(ip, port)).collect(),
+        None => tokio::net::lookup_host((host, port))
+            .await
+            .map_err(error)?
+            .collect(),
+    };
+
+    let addr = spread_addrs(addrs, spread).first().map(|addr| addr.ip());
+    addr.ok_or_else(|| error(io::ErrorKind::NotFound.into()))
+}
+
+/// Connects to the Unix domain socket at the path.
+async fn connect_unix(path: PathBuf, timeout: Option<Duration>) -> Result<Stream, BoxError> {
+    let start = Instant::now();
+    let connecting = UnixStream::connect(path);
+    let unix = match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, connecting)
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "connection timed out"))??,
+        None => connecting.await?,
+    };
+
+    let timings = ConnectTimings {
+        dns: None,
+        connect: start.elapsed(),
+        tls: None,
+        established: Instant::now(),
+        claimed: Arc::new(AtomicBool::new(false)),
+    };
+
+    Ok(Stream {
+        io: Io::Unix(unix),
+        timings,
+        proxied: false,
+    })
+}
+
+/// Connection stream produced by the [Connector].
+pub struct Stream {
+    io: Io,
+    /// Timings of the phases of establishing the connection.
+    timings: ConnectTimings,
+    /// Whether the connection was tunnelled through a proxy.
+    proxied: bool,
+}
+
+enum Io {
+    Http(TcpStream),
+    Https(Box<TlsStream<TcpStream>>),
+    Unix(UnixStream),
+}
+
+impl Connection for Stream {
+    fn connected(&self) -> Connected {
+        let connected = match &self.io {
+            Io::Http(tcp) => tcp.connected(),
+            Io::Unix(_) => Connected::new(),
+            Io::Https(tls) => {
+                let tls = tls.get_ref();
+                let connected = tls.get_ref().get_ref().connected();
+                let negotiated = tls.negotiated_alpn().ok().flatten();
+
+                if negotiated.as_deref() == Some(ALPN_H2.as_bytes()) {
+                    connected.negotiated_h2()
+                } else {
+                    connected
+                }
+            }
+        };
+
+        // Attach the timings to each response received over the connection.
+        let connected = connected.extra(self.timings.clone());
+        if self.proxied {
+            connected.extra(Proxied)
+        } else {
+            connected
+        }
+    }
+}
+
+impl AsyncRead for Stream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match &mut self.get_mut().io {
+            Io::Http(s) => Pin::new(s).poll_read(cx, buf),
+            Io::Https(s) => Pin::new(s).poll_read(cx, buf),
+            Io::Unix(s) => Pin::new(s).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Stream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match &mut self.get_mut().io {
+            Io::Http(s) => Pin::new(s).poll_write(cx, buf),
+            Io::Https(s) => Pin::new(s).poll_write(cx, buf),
+            Io::Unix(s) => Pin::new(s).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match &mut self.get_mut().io {
+            Io::Http(s) => Pin::new(s).poll_flush(cx),
+            Io::Https(s) => Pin::new(s).poll_flush(cx),
+            Io::Unix(s) => Pin::new(s).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match &mut self.get_mut().io {
+            Io::Http(s) => Pin::new(s).poll_shutdown(cx),
+            Io::Https(s) => Pin::new(s).poll_shutdown(cx),
+            Io::Unix(s) => Pin::new(s).poll_shutdown(cx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addrs(ips: &[&str]) -> Vec<SocketAddr> {
+        ips.iter()
+            .map(|ip| SocketAddr::new(ip.parse().unwrap(), 0))
+            .collect()
+    }
+
+    #[test]
+    fn spread_across_addresses() {
+        let resolved = addrs(&["10.0.0.2", "10.0.0.1", "10.0.0.3", "10.0.0.1"]);
+
+        // Without spreading, the addresses are tried in the order they were resolved.
+        assert_eq!(spread_addrs(resolved.clone(), None), resolved);
+
+        // Each slot is assigned its own address, regardless of the order of the addresses.
+        let first = |slot| spread_addrs(resolved.clone(), Some(slot))[0];
+        assert_eq!(first(0), addrs(&["10.0.0.1"])[0]);
+        assert_eq!(first(1), addrs(&["10.0.0.2"])[0]);
+        assert_eq!(first(2), addrs(&["10.0.0.3"])[0]);
+        assert_eq!(first(3), addrs(&["10.0.0.1"])[0]);
+
+        let mut reversed = resolved.clone();
+        reversed.reverse();
+        assert_eq!(spread_addrs(reversed, Some(1))[0], first(1));
+        assert!(spread_addrs(vec![], Some(1)).is_empty());
+    }
+
+    #[test]
+    fn unix_socket_uris() {
+        let uri = unix_uri("/var/run/app.sock", "/health?full=1").unwrap();
+        assert_eq!(uri.path_and_query().unwrap(), "/health?full=1");
+        let path = socket_path(&uri).unwrap();
+        assert_eq!(path.to_str(), Some("/var/run/app.sock"));
+
+        assert!(unix_uri("", "/").is_err());
+        assert!(socket_path(&"http://localhost/".parse().unwrap()).is_none());
+    }
+
+    #[tokio::test]
+    async fn resolve_overrides() {
+        let overrides = addrs(&["10.0.0.1", "10.0.0.2"]);
+        let mut resolver = TimedResolver {
+            resolver: GaiResolver::new(),
+            overrides: Some(overrides.iter().map(|a| a.ip()).collect()),
+            spread: Some(1),
+            elapsed: Arc::new(Mutex::new(None)),
+        };
+
+        // Overridden host names are not looked up.
+        let name = "backend.invalid".parse().unwrap();
+        let resolved = resolver.call(name).await.unwrap().collect::<Vec<_>>();
+        assert_eq!(resolved, addrs(&["10.0.0.2", "10.0.0.1"]));
+        assert!(resolver.elapsed.lock().unwrap().is_none());
+    }
+}
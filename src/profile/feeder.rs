@@ -0,0 +1,262 @@
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::template::Variables;
+
+/// Feeder configuration.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Config {
+    /// Path of the file containing the records.
+    pub path: PathBuf,
+    /// Format of the file. If not specified, the format is inferred from the file extension.
+    #[serde(default)]
+    pub format: Option<Format>,
+    /// Strategy used to pick the record for each request.
+    #[serde(default)]
+    pub strategy: Strategy,
+}
+
+/// Format of a feeder file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// Comma-separated values with a header row that names the fields.
+    Csv,
+    /// One JSON object per line.
+    Jsonl,
+}
+
+/// Strategy used to pick the record for each request.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Strategy {
+    /// Use each record once in file order. Running out of records before the plan has
+    /// completed is an error.
+    Sequential,
+    /// Pick records at random (with replacement).
+    Random,
+    /// Use records in file order, starting over once all records have been used.
+    #[default]
+    Circular,
+    /// Use each record once in file order and stop the test once all records have been used.
+    StopWhenExhausted,
+}
+
+/// Feeds records to requests so that their fields can be bound into request templates.
+pub struct Feeder {
+    records: Vec<Arc<Variables>>,
+    strategy: Strategy,
+    /// Index of the next record for the in-order strategies.
+    next: AtomicUsize,
+    /// Random number generator for the random strategy.
+    rng: Mutex<StdRng>,
+}
+
+impl Feeder {
+    /// Loads the feeder records.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Feeder configuration
+    /// * `seed` - Seed for random record selection (a random seed is used if not specified)
+    pub fn load(config: &Config, seed: Option<u64>) -> Result<Self> {
+        let format = match config.format {
+            Some(format) => format,
+            None => Self::infer_format(&config.path)?,
+        };
+
+        let file = File::open(&config.path)
+            .with_context(|| format!("Error opening feeder file: {}", config.path.display()))?;
+
+        let records = match format {
+            Format::Csv => Self::read_csv(file),
+            Format::Jsonl => Self::read_jsonl(file),
+        }
+        .with_context(|| format!("Error reading feeder file: {}", config.path.display()))?;
+
+        if records.is_empty() {
+            bail!("Feeder file contains no records: {}", config.path.display());
+        }
+
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        Ok(Self {
+            records: records.into_iter().map(Arc::new).collect(),
+            strategy: config.strategy,
+            next: AtomicUsize::new(0),
+            rng: Mutex::new(rng),
+        })
+    }
+
+    /// Returns the next record or `None` if the records have been exhausted.
+    pub fn next(&self) -> Option<Arc<Variables>> {
+        let len = self.records.len();
+        let index = match self.strategy {
+            Strategy::Random => self.rng.lock().unwrap().gen_range(0..len),
+            Strategy::Circular => self.next.fetch_add(1, Ordering::Relaxed) % len,
+            Strategy::Sequential | Strategy::StopWhenExhausted => {
+                let index = self.next.fetch_add(1, Ordering::Relaxed);
+                if index >= len {
+                    return None;
+                }
+
+                index
+            }
+        };
+
+        Some(self.records[index].clone())
+    }
+
+    /// Returns whether a record was requested after the records had been exhausted.
+    pub fn is_exhausted(&self) -> bool {
+        match self.strategy {
+            Strategy::Sequential | Strategy::StopWhenExhausted => {
+                self.next.load(Ordering::Relaxed) > self.records.len()
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the strategy used to pick records.
+    pub fn strategy(&self) -> Strategy {
+        self.strategy
+    }
+
+    /// Returns the number of records.
+    pub fn record_count(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Returns the names of the fields that are present in every record.
+    pub fn fields(&self) -> HashSet<&str> {
+        let mut records = self.records.iter();
+        let mut fields = records
+            .next()
+            .map(|r| r.keys().map(String::as_str).collect::<HashSet<_>>())
+            .unwrap_or_default();
+
+        for record in records {
+            fields.retain(|f| record.contains_key(*f));
+        }
+
+        fields
+    }
+
+    fn infer_format(path: &Path) -> Result<Format> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("csv") => Ok(Format::Csv),
+            Some("jsonl") | Some("ndjson") => Ok(Format::Jsonl),
+            _ => bail!(
+                "Could not infer the format of feeder file {} from its extension",
+                path.display()
+            ),
+        }
+    }
+
+    fn read_csv(file: File) -> Result<Vec<Variables>> {
+        let mut reader = csv::Reader::from_reader(file);
+        let headers = reader.headers()?.clone();
+
+        let mut records = vec![];
+        for record in reader.records() {
+            let record = record?;
+            let vars = headers
+                .iter()
+                .zip(record.iter())
+                .map(|(k, v)| (k.to_owned(), v.to_owned()))
+                .collect();
+
+            records.push(vars);
+        }
+
+        Ok(records)
+    }
+
+    fn read_jsonl(file: File) -> Result<Vec<Variables>> {
+        let mut records = vec![];
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let object: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&line)
+                .with_context(|| format!("Invalid JSON object on line {}", i + 1))?;
+
+            // String values are bound as is whereas other values are bound as JSON.
+            let vars = object
+                .into_iter()
+                .map(|(k, v)| match v {
+                    serde_json::Value::String(s) => (k, s),
+                    v => (k, v.to_string()),
+                })
+                .collect();
+
+            records.push(vars);
+        }
+
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Loads a feeder from a temporary file with the extension and contents.
+    fn load(extension: &str, contents: &str, strategy: Strategy) -> Feeder {
+        let file = tempfile::Builder::new()
+            .suffix(extension)
+            .tempfile()
+            .unwrap();
+        std::fs::write(file.path(), contents).unwrap();
+
+        let config = Config {
+            path: file.path().to_owned(),
+            format: None,
+            strategy,
+        };
+
+        Feeder::load(&config, Some(42)).unwrap()
+    }
+
+    #[test]
+    fn stop_when_exhausted() {
+        let csv = "user_id,token\n1,a\n2,b\n";
+        let feeder = load(".csv", csv, Strategy::StopWhenExhausted);
+        assert_eq!(feeder.fields(), HashSet::from(["user_id", "token"]));
+
+        assert_eq!(feeder.next().unwrap()["user_id"], "1");
+        assert_eq!(feeder.next().unwrap()["token"], "b");
+        assert!(!feeder.is_exhausted());
+        assert!(feeder.next().is_none());
+        assert!(feeder.is_exhausted());
+    }
+
+    #[test]
+    fn circular_jsonl() {
+        let jsonl = "{\"id\": 1, \"name\": \"a\"}\n\n{\"id\": 2}\n";
+        let feeder = load(".jsonl", jsonl, Strategy::Circular);
+        assert_eq!(feeder.fields(), HashSet::from(["id"]));
+
+        let ids = (0..3).map(|_| feeder.next().unwrap()["id"].clone());
+        assert_eq!(ids.collect::<Vec<_>>(), vec!["1", "2", "1"]);
+        assert!(!feeder.is_exhausted());
+    }
+}
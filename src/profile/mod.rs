@@ -1,14 +1,40 @@
+mod assertion;
+mod client;
 mod config;
+mod connector;
+mod feeder;
 mod metrics;
 mod plan;
 mod profiler;
+mod proxy;
 mod report;
+mod request;
+mod resolve;
+mod scenario;
 mod signaller;
+mod target;
+mod template;
+mod tls;
+mod trace;
+mod users;
 
 pub use self::{
+    assertion::Assertion,
     config::Config,
-    plan::{Plan, PlanSegment},
+    feeder::{
+        Config as FeederConfig, Feeder, Format as FeederFormat, Strategy as FeederStrategy,
+    },
+    plan::{Arrival, Plan, PlanSegment},
     profiler::Profiler,
-    report::Report,
+    proxy::{Config as ProxyConfig, Proxy},
+    report::{InterimReport, Report},
+    request::RequestTemplate,
+    resolve::Override as ResolveOverride,
+    scenario::Scenario,
     signaller::{Kind as SignallerKind, Signal, Signaller},
+    target::{Selector, Strategy as TargetSelection, Target},
+    template::{Context as TemplateContext, Template},
+    tls::Config as TlsConfig,
+    trace::{Interpolation as TraceInterpolation, Trace},
+    users::{ThinkTime, UserPlan, UserSegment},
 };
@@ -1,8 +1,15 @@
-use std::time::{Duration, Instant};
+use std::{
+    f64::consts::TAU,
+    time::{Duration, Instant},
+};
 
+use anyhow::{bail, Result};
 use metron::Rate;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
 
+use super::trace::Trace;
+
 /// Timing plan for outbound requests.
 ///
 /// The plan dictates when requests should be sent should be sent to the
@@ -11,6 +18,74 @@ use serde::{Deserialize, Serialize};
 pub struct Plan {
     /// Segments that define how the request rate varies over the plan.
     segments: Vec<PlanSegment>,
+    /// Process that decides how requests arrive at the rate set by the segments.
+    #[serde(default)]
+    arrival: Arrival,
+    /// Seed for the arrival process (a random seed is used if not specified).
+    #[serde(default)]
+    seed: Option<u64>,
+}
+
+/// Describes how the gaps between requests are distributed.
+///
+/// Whichever process is used, the mean gap between requests matches the interval implied by
+/// the rate set by the plan so that the plan's mean rate is maintained.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Arrival {
+    /// Requests are evenly spaced.
+    #[default]
+    Constant,
+
+    /// Requests arrive as a Poisson process (i.e. the gaps between requests are
+    /// exponentially distributed).
+    Poisson,
+
+    /// Each gap is uniformly distributed within `jitter` (a fraction between 0 and 1) of the
+    /// interval implied by the rate.
+    Uniform { jitter: f64 },
+
+    /// The gaps between requests follow a Pareto distribution with the given shape, which
+    /// produces occasional long gaps followed by bursts of requests. Smaller shapes (which
+    /// must be greater than 1) have heavier tails.
+    Pareto { shape: f64 },
+}
+
+impl Arrival {
+    /// Checks that the parameters of the arrival process are valid.
+    pub fn validate(&self) -> Result<()> {
+        match *self {
+            Arrival::Uniform { jitter } if !(0.0..=1.0).contains(&jitter) => {
+                bail!("Uniform arrival jitter must be between 0 and 1")
+            }
+            Arrival::Pareto { shape } if shape.is_nan() || shape <= 1.0 => {
+                bail!("Pareto arrival shape must be greater than 1")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Samples the factor that the interval until the next request is scaled by. Factors
+    /// have a mean of 1.
+    fn sample<R: Rng>(&self, rng: &mut R) -> f64 {
+        match *self {
+            Arrival::Constant => 1.0,
+            Arrival::Poisson => {
+                let u: f64 = rng.gen_range(0.0..1.0);
+                -(1.0 - u).ln()
+            }
+            Arrival::Uniform { jitter } => {
+                let u: f64 = rng.gen_range(0.0..1.0);
+                1.0 + jitter * (2.0 * u - 1.0)
+            }
+            Arrival::Pareto { shape } => {
+                // The scale is chosen so that the mean of the distribution is 1.
+                let u: f64 = rng.gen_range(0.0..1.0);
+                let scale = (shape - 1.0) / shape;
+                scale / (1.0 - u).powf(1.0 / shape)
+            }
+        }
+    }
 }
 
 /// Describes how request rate should be treated over a given duration.
@@ -32,6 +107,46 @@ pub enum PlanSegment {
         #[serde(with = "humantime_serde")]
         duration: Duration,
     },
+
+    /// Rate should step from the start rate to the end rate in equal steps, each of which
+    /// is held for the given duration.
+    Step {
+        rate_start: Rate,
+        rate_end: Rate,
+        steps: u32,
+        #[serde(with = "humantime_serde")]
+        step_duration: Duration,
+    },
+
+    /// Rate should oscillate around the base rate over the given duration (or forever),
+    /// rising to `rate + amplitude` and falling to `rate - amplitude` over each period.
+    Sine {
+        rate: Rate,
+        amplitude: Rate,
+        #[serde(with = "humantime_serde")]
+        period: Duration,
+        #[serde(default)]
+        #[serde(with = "humantime_serde")]
+        duration: Option<Duration>,
+    },
+
+    /// Rate should vary exponentially over the given duration (i.e. the rate grows or
+    /// shrinks by the same factor over each equal period of time).
+    Exponential {
+        rate_start: Rate,
+        rate_end: Rate,
+        #[serde(with = "humantime_serde")]
+        duration: Duration,
+    },
+
+    /// No requests should be sent over the given duration.
+    Pause {
+        #[serde(with = "humantime_serde")]
+        duration: Duration,
+    },
+
+    /// Rate should follow a time series of rates that is read from a file.
+    Trace(Trace),
 }
 
 impl PlanSegment {
@@ -39,6 +154,198 @@ impl PlanSegment {
         match self {
             PlanSegment::Fixed { duration, .. } => *duration,
             PlanSegment::Linear { duration, .. } => Some(*duration),
+            PlanSegment::Step {
+                steps,
+                step_duration,
+                ..
+            } => Some(*step_duration * *steps),
+            PlanSegment::Sine { duration, .. } => *duration,
+            PlanSegment::Exponential { duration, .. } => Some(*duration),
+            PlanSegment::Pause { duration } => Some(*duration),
+            PlanSegment::Trace(trace) => Some(trace.duration()),
+        }
+    }
+
+    /// Checks that the segment describes a schedule that can be generated.
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            PlanSegment::Step {
+                steps,
+                step_duration,
+                ..
+            } => {
+                if *steps == 0 {
+                    bail!("Step segments must have at least one step");
+                }
+
+                if step_duration.is_zero() {
+                    bail!("Step segments must have a non-zero step duration");
+                }
+            }
+            PlanSegment::Sine {
+                rate,
+                amplitude,
+                period,
+                ..
+            } => {
+                if amplitude.per_sec() >= rate.per_sec() {
+                    bail!("The amplitude of a sine segment must be less than its rate");
+                }
+
+                if period.is_zero() {
+                    bail!("Sine segments must have a non-zero period");
+                }
+            }
+            PlanSegment::Trace(trace) => trace.validate()?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Calculates the number of requests that should have been sent `progress` into the
+    /// segment (i.e. the integral of the segment's rate up to `progress`).
+    ///
+    /// The number is fractional as requests are not necessarily sent on whole seconds.
+    fn requests_until(&self, progress: Duration) -> f64 {
+        let end = self.duration().unwrap_or(Duration::MAX);
+        let t = progress.min(end).as_secs_f64();
+        match *self {
+            PlanSegment::Fixed { rate, .. } => rate.per_sec() * t,
+            PlanSegment::Linear {
+                rate_start,
+                rate_end,
+                duration,
+            } => {
+                let (start, end) = (rate_start.per_sec(), rate_end.per_sec());
+                let slope = (end - start) / duration.as_secs_f64();
+                start * t + slope * t * t / 2.0
+            }
+            PlanSegment::Step { step_duration, .. } => {
+                let step_secs = step_duration.as_secs_f64();
+                let complete = (t / step_secs) as u32;
+                let completed = (0..complete).map(|step| self.step_rate(step)).sum::<f64>();
+                let partial = t - f64::from(complete) * step_secs;
+                completed * step_secs + self.step_rate(complete) * partial
+            }
+            PlanSegment::Sine {
+                rate,
+                amplitude,
+                period,
+                ..
+            } => {
+                // The integral of the sine wave itself is zero over each complete period.
+                let omega = TAU / period.as_secs_f64();
+                rate.per_sec() * t + amplitude.per_sec() * (1.0 - (omega * t).cos()) / omega
+            }
+            PlanSegment::Exponential {
+                rate_start,
+                rate_end,
+                duration,
+            } => {
+                let (start, end) = (rate_start.per_sec(), rate_end.per_sec());
+                let growth = (end / start).ln() / duration.as_secs_f64();
+                if growth == 0.0 {
+                    start * t
+                } else {
+                    start * (growth * t).exp_m1() / growth
+                }
+            }
+            PlanSegment::Pause { .. } => 0.0,
+            PlanSegment::Trace(ref trace) => trace.requests_until(t),
+        }
+    }
+
+    /// Calculates how far into the segment the given number of requests should have been
+    /// sent (i.e. the inverse of [`requests_until`][Self::requests_until]).
+    fn time_of(&self, requests: f64) -> Duration {
+        let secs = match *self {
+            PlanSegment::Fixed { rate, .. } => requests / rate.per_sec(),
+            PlanSegment::Linear {
+                rate_start,
+                rate_end,
+                duration,
+            } => {
+                // Solves the quadratic in a form that avoids cancellation when the slope is
+                // small (or zero).
+                let (start, end) = (rate_start.per_sec(), rate_end.per_sec());
+                let slope = (end - start) / duration.as_secs_f64();
+                let discriminant = (start * start + 2.0 * slope * requests).max(0.0);
+                2.0 * requests / (start + discriminant.sqrt())
+            }
+            PlanSegment::Step { step_duration, .. } => {
+                let step_secs = step_duration.as_secs_f64();
+                let mut remaining = requests;
+                let mut step = 0;
+                while remaining >= self.step_rate(step) * step_secs && step + 1 < self.steps() {
+                    remaining -= self.step_rate(step) * step_secs;
+                    step += 1;
+                }
+
+                f64::from(step) * step_secs + remaining / self.step_rate(step)
+            }
+            PlanSegment::Sine {
+                rate, amplitude, ..
+            } => {
+                // There is no closed form so we bisect between the times that the requests
+                // would be sent at the minimum and maximum rates.
+                let (rate, amplitude) = (rate.per_sec(), amplitude.per_sec());
+                let mut low = requests / (rate + amplitude);
+                let mut high = requests / (rate - amplitude);
+                for _ in 0..64 {
+                    let mid = (low + high) / 2.0;
+                    if self.requests_until(Duration::from_secs_f64(mid)) < requests {
+                        low = mid;
+                    } else {
+                        high = mid;
+                    }
+                }
+
+                high
+            }
+            PlanSegment::Exponential {
+                rate_start,
+                rate_end,
+                duration,
+            } => {
+                let (start, end) = (rate_start.per_sec(), rate_end.per_sec());
+                let growth = (end / start).ln() / duration.as_secs_f64();
+                if growth == 0.0 {
+                    requests / start
+                } else {
+                    (growth * requests / start).ln_1p() / growth
+                }
+            }
+            PlanSegment::Pause { .. } => 0.0,
+            PlanSegment::Trace(ref trace) => trace.time_of(requests),
+        };
+
+        Duration::from_secs_f64(secs)
+    }
+
+    /// Returns the rate (in requests per second) of the step of a step segment.
+    fn step_rate(&self, step: u32) -> f64 {
+        match *self {
+            PlanSegment::Step {
+                rate_start,
+                rate_end,
+                steps,
+                ..
+            } if steps > 1 => {
+                let (start, end) = (rate_start.per_sec(), rate_end.per_sec());
+                let step = step.min(steps - 1);
+                start + (end - start) * f64::from(step) / f64::from(steps - 1)
+            }
+            PlanSegment::Step { rate_start, .. } => rate_start.per_sec(),
+            _ => 0.0,
+        }
+    }
+
+    /// Returns the number of steps of a step segment.
+    fn steps(&self) -> u32 {
+        match *self {
+            PlanSegment::Step { steps, .. } => steps,
+            _ => 1,
         }
     }
 }
@@ -61,46 +368,45 @@ impl Plan {
                 }
             })
     }
-
-    /// Finds the `PlanSegment` that `progress` falls into.
-    ///
-    /// If the returned value is `None` then we have completed the plan.
-    fn find_segment(&self, progress: Duration) -> Option<PlanSegment> {
-        let mut total = Duration::from_secs(0);
-        for seg in &self.segments {
-            if let Some(d) = seg.duration() {
-                total += d;
-                if progress < total {
-                    return Some(seg.clone());
-                }
-            } else {
-                // The plan runs forever.
-                return Some(seg.clone());
-            }
-        }
-
-        None
-    }
 }
 
+/// Iterator over the instants at which requests should be sent according to a [Plan].
+///
+/// Rather than stepping from one request to the next by the interval implied by the rate at
+/// the time, the instant of each request is found by inverting the integral of the rate
+/// over the segment. This means that the number of requests sent by each segment (and by
+/// any point in the segment) is exactly what the rate dictates regardless of how long the
+/// segment is. The arrival process randomizes how far apart the requests are in terms of
+/// the number of requests, which preserves the mean rate.
 pub struct Ticks<'a> {
     /// The plan.
     plan: &'a Plan,
-    /// Cached plan duration.
-    duration: Option<Duration>,
     /// When the plan was started.
     start: Instant,
-    /// Previously returned instant (none if not started).
-    prev: Option<Instant>,
+    /// Index of the segment that the next tick falls within.
+    segment: usize,
+    /// Offset of the segment from the start of the plan.
+    segment_start: Duration,
+    /// Number of requests into the segment at which the next tick falls.
+    position: f64,
+    /// Source of randomness for the arrival process.
+    rng: StdRng,
 }
 
 impl<'a> Ticks<'a> {
     pub fn new(plan: &'a Plan, start: Instant) -> Self {
+        let rng = match plan.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
         Self {
             plan,
-            duration: plan.calculate_duration(),
             start,
-            prev: None,
+            segment: 0,
+            segment_start: Duration::ZERO,
+            position: 0.0,
+            rng,
         }
     }
 }
@@ -109,44 +415,25 @@ impl<'a> Iterator for Ticks<'a> {
     type Item = Instant;
 
     fn next(&mut self) -> Option<Self::Item> {
-        // How far into the plan are we?
-        let progress = self.prev.unwrap_or(self.start) - self.start;
-
-        if let Some(block) = self.plan.find_segment(progress) {
-            // Calculate the next value in the series.
-            let next = match block {
-                PlanSegment::Fixed { rate, .. } => self
-                    .prev
-                    .map(|t| t + rate.as_interval())
-                    .unwrap_or(self.start),
-
-                PlanSegment::Linear {
-                    rate_start,
-                    rate_end,
-                    duration,
-                } => {
-                    let ramp_start = rate_start.as_interval().as_secs_f32();
-                    let ramp_end = rate_end.as_interval().as_secs_f32();
-                    let duration = duration.as_secs_f32();
-                    let progress = progress.as_secs_f32();
-
-                    let ramp_progress_factor =
-                        (ramp_start - ramp_end) * (progress / duration).min(1.0);
-                    let delta = Duration::from_secs_f32(ramp_start - ramp_progress_factor);
-
-                    self.prev.map(|t| t + delta).unwrap_or(self.start)
+        loop {
+            let seg = self.plan.segments.get(self.segment)?;
+
+            // Move on to the next segment once this segment's requests have all been sent,
+            // carrying over how far the next tick is past the end of the segment.
+            if let Some(duration) = seg.duration() {
+                let total = seg.requests_until(duration);
+                if self.position >= total {
+                    self.position -= total;
+                    self.segment_start += duration;
+                    self.segment += 1;
+                    continue;
                 }
-            };
+            }
 
-            self.prev = Some(next);
+            let offset = seg.time_of(self.position);
+            self.position += self.plan.arrival.sample(&mut self.rng);
 
-            if let Some(d) = self.duration && next - self.start >= d {
-                None
-            } else {
-                self.prev
-            }
-        } else {
-            None
+            return Some(self.start + self.segment_start + offset);
         }
     }
 }
@@ -174,7 +461,11 @@ pub struct Builder {
 impl Builder {
     pub fn new() -> Self {
         Self {
-            plan: Plan { segments: vec![] },
+            plan: Plan {
+                segments: vec![],
+                arrival: Arrival::default(),
+                seed: None,
+            },
         }
     }
 
@@ -186,7 +477,249 @@ impl Builder {
         self
     }
 
+    /// Sets the arrival process and the seed that it uses (a random seed is used if not
+    /// specified).
+    pub fn arrival(mut self, arrival: Arrival, seed: Option<u64>) -> Builder {
+        self.plan.arrival = arrival;
+        self.plan.seed = seed;
+        self
+    }
+
     pub fn build(self) -> Plan {
         self.plan
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the offsets of the ticks generated by a seeded plan with the arrival process.
+    fn arrivals(arrival: Arrival, seed: u64) -> Vec<Duration> {
+        let segments = [PlanSegment::Fixed {
+            rate: "1000".parse().unwrap(),
+            duration: Some(Duration::from_secs(10)),
+        }];
+
+        let plan = Builder::new()
+            .segments(&segments)
+            .arrival(arrival, Some(seed))
+            .build();
+
+        let start = Instant::now();
+        plan.ticks(start).map(|t| t - start).collect()
+    }
+
+    /// Counts the ticks generated by the plan in each second.
+    fn ticks_per_sec(segments: &str) -> Vec<usize> {
+        let mut segments: Vec<PlanSegment> = serde_yaml::from_str(segments).unwrap();
+        for seg in &mut segments {
+            if let PlanSegment::Trace(trace) = seg {
+                trace.load().unwrap();
+            }
+
+            seg.validate().unwrap();
+        }
+
+        let plan = Builder::new().segments(&segments).build();
+        let start = Instant::now();
+        let mut counts = vec![];
+        for t in plan.ticks(start) {
+            let sec = (t - start).as_secs() as usize;
+            counts.resize(counts.len().max(sec + 1), 0);
+            counts[sec] += 1;
+        }
+
+        counts
+    }
+
+    #[test]
+    fn step_ticks() {
+        let counts = ticks_per_sec(
+            "[{kind: step, rate_start: '10', rate_end: '40', steps: 4, step_duration: 1s}]",
+        );
+        assert_eq!(counts, [10, 20, 30, 40]);
+    }
+
+    #[test]
+    fn sine_ticks() {
+        // The sine segment starts once the fixed segment has completed.
+        let counts = ticks_per_sec(
+            "[{kind: fixed, rate: '100', duration: 1s},
+              {kind: sine, rate: '100', amplitude: '50', period: 4s, duration: 4s}]",
+        );
+        assert_eq!(counts.len(), 5);
+        assert_eq!(counts[0], 100);
+
+        // The rate rises during the first half of each period and falls during the second.
+        assert!(counts[1] > 125 && counts[2] > 125, "{:?}", counts);
+        assert!(counts[3] < 75 && counts[4] < 75, "{:?}", counts);
+
+        let total = counts[1..].iter().sum::<usize>();
+        assert!(total.abs_diff(400) <= 1, "{}", total);
+    }
+
+    #[test]
+    fn exponential_ticks() {
+        let counts = ticks_per_sec(
+            "[{kind: exponential, rate_start: '10', rate_end: '1000', duration: 2s}]",
+        );
+        assert_eq!(counts.len(), 2);
+
+        // The rate grows by a factor of 10 over each second, so the number of requests
+        // sent is the integral of 10 * 100^(t / 2).
+        let expected = |from: f64, to: f64| {
+            let k = 100f64.ln() / 2.0;
+            10.0 * ((k * to).exp() - (k * from).exp()) / k
+        };
+
+        for (sec, &count) in counts.iter().enumerate() {
+            let expected = expected(sec as f64, sec as f64 + 1.0);
+            assert!((count as f64 - expected).abs() <= 1.0, "{:?}", counts);
+        }
+    }
+
+    /// Returns the offsets of the ticks generated by the plan.
+    fn offsets(segments: &[PlanSegment]) -> Vec<Duration> {
+        let plan = Builder::new().segments(segments).build();
+        let start = Instant::now();
+        plan.ticks(start).map(|t| t - start).collect()
+    }
+
+    #[test]
+    fn linear_ramp_ticks() {
+        let ticks = offsets(&[PlanSegment::Linear {
+            rate_start: "10".parse().unwrap(),
+            rate_end: "1000".parse().unwrap(),
+            duration: Duration::from_secs(60),
+        }]);
+
+        // The ramp sends the integral of its rate: 60s at an average of 505 RPS.
+        assert_eq!(ticks.len(), 30_300);
+        assert!(ticks.windows(2).all(|w| w[0] < w[1]));
+        assert!(*ticks.last().unwrap() < Duration::from_secs(60));
+
+        // The k-th request is sent at the time t where 10t + 8.25t^2 = k.
+        for (k, tick) in ticks.iter().enumerate() {
+            let expected = (-10.0 + (100.0 + 33.0 * k as f64).sqrt()) / 16.5;
+            let error = (tick.as_secs_f64() - expected).abs();
+            assert!(error < 1e-6, "request {} sent at {:?}", k, tick);
+        }
+    }
+
+    #[test]
+    fn long_high_rate_ramp_ticks() {
+        // Ramp down from and then back up to 5000 RPS over 10 minutes each, with the
+        // requests that fall on the boundary between the ramps carried over.
+        let ramp = |from: &str, to: &str| PlanSegment::Linear {
+            rate_start: from.parse().unwrap(),
+            rate_end: to.parse().unwrap(),
+            duration: Duration::from_secs(600),
+        };
+
+        let ticks = offsets(&[ramp("5000", "100"), ramp("100", "5000")]);
+        assert_eq!(ticks.len(), 2 * 1_530_000);
+        assert!(ticks.windows(2).all(|w| w[0] <= w[1]));
+        assert!(*ticks.last().unwrap() < Duration::from_secs(1200));
+
+        // The cumulative number of requests sent matches the integral of the rate
+        // throughout both ramps.
+        let sent_by = |t: f64| ticks.partition_point(|tick| tick.as_secs_f64() < t);
+        let integral = |t: f64| {
+            let down = |t: f64| 5000.0 * t - 4900.0 / 1200.0 * t * t;
+            if t <= 600.0 {
+                down(t)
+            } else {
+                let t = t - 600.0;
+                down(600.0) + 100.0 * t + 4900.0 / 1200.0 * t * t
+            }
+        };
+
+        for t in (0..1200).map(|t| t as f64 + 0.37) {
+            let expected = integral(t);
+            assert!((sent_by(t) as f64 - expected).abs() <= 1.0, "at {}s", t);
+        }
+    }
+
+    #[test]
+    fn fixed_rate_ticks_do_not_drift() {
+        let ticks = offsets(&[PlanSegment::Fixed {
+            rate: "3".parse().unwrap(),
+            duration: Some(Duration::from_secs(24 * 60 * 60)),
+        }]);
+
+        assert_eq!(ticks.len(), 3 * 24 * 60 * 60);
+        let last = Duration::from_secs(24 * 60 * 60) - Duration::from_nanos(333_333_333);
+        assert!(last.abs_diff(*ticks.last().unwrap()) < Duration::from_micros(1));
+    }
+
+    #[test]
+    fn pause_ticks() {
+        let counts = ticks_per_sec(
+            "[{kind: pause, duration: 1s},
+              {kind: fixed, rate: 10, duration: 1s},
+              {kind: pause, duration: 2s},
+              {kind: fixed, rate: 1/500ms, duration: 1s},
+              {kind: pause, duration: 1s}]",
+        );
+        assert_eq!(counts, [0, 10, 0, 0, 2]);
+    }
+
+    #[test]
+    fn trace_ticks() {
+        // Replay two minutes of traffic in two seconds at double the recorded rates.
+        let file = tempfile::Builder::new().suffix(".csv").tempfile().unwrap();
+        std::fs::write(file.path(), "0,10\n60,20\n120,0\n").unwrap();
+        let segments = format!(
+            "[{{kind: fixed, rate: 5, duration: 1s}},
+              {{kind: trace, path: {}, interpolation: step, duration: 2s, scale: 2}}]",
+            file.path().display()
+        );
+
+        let counts = ticks_per_sec(&segments);
+        assert_eq!(counts, [5, 20, 40]);
+
+        let mut segments: Vec<PlanSegment> = serde_yaml::from_str(&segments).unwrap();
+        if let PlanSegment::Trace(trace) = &mut segments[1] {
+            trace.load().unwrap();
+        }
+
+        let plan = Builder::new().segments(&segments).build();
+        assert_eq!(plan.calculate_duration(), Some(Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn arrival_processes() {
+        let processes = [
+            Arrival::Constant,
+            Arrival::Poisson,
+            Arrival::Uniform { jitter: 0.5 },
+            Arrival::Pareto { shape: 2.5 },
+        ];
+
+        for arrival in processes {
+            arrival.validate().unwrap();
+
+            // The mean rate of 1000 RPS over 10 seconds is maintained.
+            let ticks = arrivals(arrival, 42);
+            assert!(ticks.len().abs_diff(10_000) <= 300, "{:?}", arrival);
+
+            // The same seed produces the same arrivals.
+            assert_eq!(ticks, arrivals(arrival, 42), "{:?}", arrival);
+        }
+
+        // Gaps vary unless arrivals are constant.
+        let gaps = |ticks: Vec<Duration>| {
+            let mut gaps = ticks.windows(2).map(|w| w[1] - w[0]).collect::<Vec<_>>();
+            gaps.sort();
+            gaps.dedup();
+            gaps.len()
+        };
+
+        assert!(gaps(arrivals(Arrival::Constant, 42)) <= 2);
+        assert!(gaps(arrivals(Arrival::Poisson, 42)) > 1000);
+
+        assert!(Arrival::Uniform { jitter: 1.5 }.validate().is_err());
+        assert!(Arrival::Pareto { shape: 1.0 }.validate().is_err());
+    }
+}
@@ -1,12 +1,36 @@
-use std::time::{Duration, Instant};
+use std::{
+    collections::HashSet,
+    future::Future,
+    mem,
+    net::IpAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
-use hyper::{Client, Uri};
-use hyper_tls::HttpsConnector;
+use anyhow::bail;
+use hyper::{body::HttpBody, client::connect::HttpInfo, HeaderMap};
+use metron::ResponseBody;
+use serde::Serialize;
 use thiserror::Error;
-use tokio::sync::mpsc;
-use url::Url;
+use tokio::sync::{mpsc, oneshot, watch, Mutex, Semaphore};
+
+use super::{
+    client::{Client, ClientError},
+    connector::{ConnectTimings, Proxied},
+    metrics, plan, report,
+    request::Request,
+    scenario::Extractor,
+    target::Selection,
+    template::Variables,
+    Config, Feeder, FeederStrategy, InterimReport, Report, Scenario, Selector, Signaller,
+    ThinkTime, UserPlan,
+};
 
-use super::{metrics, plan, report, Config, Report, Signaller};
+/// How often the closed-loop driver re-evaluates the number of virtual users.
+const USER_PLAN_RESOLUTION: Duration = Duration::from_millis(100);
 
 #[derive(Error, Debug)]
 #[non_exhaustive]
@@ -20,6 +44,12 @@ pub enum Error {
         report: Report,
     },
 
+    #[error("Response failed assertion {assertion}")]
+    AssertionFailed { assertion: String, report: Report },
+
+    #[error("Feeder records were exhausted after {records} records")]
+    FeederExhausted { records: usize, report: Report },
+
     #[error(transparent)]
     Unexpected(#[from] anyhow::Error),
 }
@@ -29,6 +59,8 @@ impl Error {
         match self {
             Error::HttpResponse { report, .. } => Some(report),
             Error::HttpRequest { report, .. } => Some(report),
+            Error::AssertionFailed { report, .. } => Some(report),
+            Error::FeederExhausted { report, .. } => Some(report),
             _ => None,
         }
     }
@@ -36,37 +68,173 @@ impl Error {
 
 pub struct Profiler {
     config: Config,
+    /// Set to `true` to stop sending requests before the plan completes.
+    stop: watch::Sender<bool>,
+    /// Requests for interim reports, each of which is answered on the enclosed channel.
+    interim_tx: mpsc::Sender<oneshot::Sender<InterimReport>>,
+    interim_rx: Mutex<mpsc::Receiver<oneshot::Sender<InterimReport>>>,
 }
 
 impl Profiler {
     pub fn new(config: Config) -> Self {
-        Self { config }
+        let (stop, _) = watch::channel(false);
+        let (interim_tx, interim_rx) = mpsc::channel(1);
+        Self {
+            config,
+            stop,
+            interim_tx,
+            interim_rx: Mutex::new(interim_rx),
+        }
+    }
+
+    /// Stops sending requests (e.g. because the user pressed Ctrl-C).
+    ///
+    /// Requests that are already in flight are given until the drain timeout to complete,
+    /// after which the report is built from the samples collected so far and marked as
+    /// interrupted.
+    pub fn stop(&self) {
+        self.stop.send_replace(true);
+    }
+
+    /// Returns a snapshot of the report while the test keeps running, covering both the run
+    /// so far and the interval since the previous interim report.
+    ///
+    /// Returns `None` if the run has already completed.
+    pub async fn interim_report(&self) -> Option<InterimReport> {
+        let (tx, rx) = oneshot::channel();
+        self.interim_tx.send(tx).await.ok()?;
+        rx.await.ok()
     }
 
     pub async fn run(&self) -> Result<Report, Error> {
-        let target_urls = self.config.targets.clone();
-        let target_uris = target_urls
-            .iter()
-            .map(|uri| uri.to_string().parse::<hyper::Uri>().unwrap())
-            .collect::<Vec<Uri>>();
+        let feeder = match &self.config.feeder {
+            Some(config) => Some(Arc::new(Feeder::load(config, self.config.seed)?)),
+            None => None,
+        };
 
-        let http_method: hyper::Method = self.config.http_method.into();
+        let fields = feeder.as_ref().map(|f| f.fields()).unwrap_or_default();
+        let workload = match &self.config.scenario {
+            Some(scenario) => self.prepare_scenario(scenario, fields)?,
+            None => self.prepare_requests(fields)?,
+        };
 
-        let payload = self.config.payload.clone().unwrap_or_default();
+        let requester = Requester {
+            client: Client::new(&self.config)?,
+            workload: Arc::new(workload),
+            body: self.config.response_body,
+            seq: Arc::new(AtomicU64::new(0)),
+            feeder: feeder.clone(),
+            timeout: self.config.timeout,
+        };
 
         let (tx, rx) = mpsc::channel(1024);
-        let plan = plan::Builder::new().segments(&self.config.segments).build();
+        if self.config.users.is_empty() {
+            self.run_open_loop(requester, tx, self.stop.subscribe());
+        } else {
+            self.run_closed_loop(requester, tx, self.stop.subscribe());
+        }
+
+        let mut interim_rx = self.interim_rx.lock().await;
+        let report = self.build_report(rx, &mut interim_rx).await;
+
+        // Interim reports can no longer be produced, so drop any outstanding requests.
+        interim_rx.close();
+        while interim_rx.try_recv().is_ok() {}
+
+        let report = report?;
+
+        // Running out of records is only an error for the sequential feeder strategy.
+        if let Some(feeder) = feeder
+            && feeder.strategy() == FeederStrategy::Sequential
+            && feeder.is_exhausted()
+        {
+            return Err(Error::FeederExhausted {
+                records: feeder.record_count(),
+                report,
+            });
+        }
+
+        Ok(report)
+    }
+
+    /// Prepares the plain targets and request templates, which are sampled independently.
+    ///
+    /// # Arguments
+    ///
+    /// * `vars` - Names of the variables that the feeder provides
+    fn prepare_requests(&self, vars: HashSet<&str>) -> anyhow::Result<Workload> {
+        // Plain targets share the profile-wide method, headers and payload whereas request
+        // templates define their own.
+        let mut requests = vec![];
+        for target in &self.config.targets {
+            requests.push(Request::from_target(target, &self.config)?);
+        }
+
+        for template in &self.config.requests {
+            requests.push(Request::from_template(template, &self.config)?);
+        }
+
+        for request in &requests {
+            check_variables(request, &vars)?;
+        }
+
+        let weights = requests.iter().map(|r| r.weight).collect::<Vec<_>>();
+        let selector = Selector::new(&weights, self.config.target_selection, self.config.seed)?;
+
+        Ok(Workload::Requests { requests, selector })
+    }
+
+    /// Prepares the steps of the scenario.
+    ///
+    /// # Arguments
+    ///
+    /// * `scenario` - Scenario to prepare
+    /// * `vars` - Names of the variables that the feeder provides. Each step may also
+    ///   reference the variables extracted by the steps before it.
+    fn prepare_scenario<'a>(
+        &self,
+        scenario: &'a Scenario,
+        mut vars: HashSet<&'a str>,
+    ) -> anyhow::Result<Workload> {
+        let mut steps = vec![];
+        for step in &scenario.steps {
+            let request = Request::from_step(step, &self.config)?;
+            check_variables(&request, &vars)?;
+
+            vars.extend(step.extract.iter().map(|e| e.var.as_str()));
+            steps.push((request, step.extract.clone()));
+        }
+
+        Ok(Workload::Scenario {
+            name: scenario.name.clone(),
+            steps,
+        })
+    }
+
+    /// Sends requests at the rate dictated by the plan, irrespective of how long the target
+    /// takes to respond.
+    fn run_open_loop(
+        &self,
+        requester: Requester,
+        tx: mpsc::Sender<Event>,
+        mut stop: watch::Receiver<bool>,
+    ) {
+        let in_flight = self.config.max_in_flight.map(|n| Arc::new(Semaphore::new(n)));
+        let plan = plan::Builder::new()
+            .segments(&self.config.segments)
+            .arrival(self.config.arrival, self.config.seed)
+            .build();
         let mut signaller = Signaller::start(self.config.signaller_kind, plan.clone());
 
         tokio::spawn(async move {
-            let https = HttpsConnector::new();
-            let client = Client::builder().build::<_, hyper::Body>(https);
-            let mut target_idx = 0;
-
             let start = Instant::now();
             let stop_at = plan.calculate_duration().map(|d| start + d);
 
-            while let Some(sig) = signaller.recv().await {
+            // Dropping the signaller when we quit stops it from generating further signals.
+            while let Some(sig) = tokio::select! {
+                sig = signaller.recv() => sig,
+                _ = stop.wait_for(|&stop| stop) => None,
+            } {
                 // Quit if we've hit the time limit.
                 if let Some(stop_at) = stop_at && Instant::now() >= stop_at {
                     break;
@@ -78,62 +246,156 @@ impl Profiler {
                     break;
                 }
 
-                // Round-robin through the target URIs.
-                let target_uri = target_uris[target_idx].clone();
-                let target_url = target_urls[target_idx].clone();
-                target_idx = (target_idx + 1) % target_uris.len();
+                let work = requester.next_work();
+
+                // Drop the work rather than perform it if the maximum number of in-flight
+                // requests has been reached.
+                let permit = match &in_flight {
+                    Some(in_flight) => match in_flight.clone().try_acquire_owned() {
+                        Ok(permit) => Some(permit),
+                        Err(_) => {
+                            let dropped = requester.dropped(&work);
+                            if tx.send(Event::Dropped(dropped)).await.is_err() {
+                                break;
+                            }
+
+                            continue;
+                        }
+                    },
+                    None => None,
+                };
+
+                // Stop sending requests once the feeder's records have been exhausted.
+                let Ok(record) = requester.next_record() else {
+                    break;
+                };
 
                 // Clone other items that need to be moved into the spawned task below.
-                let client = client.clone();
+                let requester = requester.clone();
                 let tx = tx.clone();
-                let http_method = http_method.clone();
-                let payload = payload.clone();
 
-                // Send Result<Sample> down the channel
+                // Send the resulting samples down the channel
                 tokio::spawn(async move {
-                    let req = hyper::Request::builder()
-                        .method(http_method)
-                        .uri(target_uri)
-                        .body(hyper::Body::from(payload))?;
-
-                    let sent = Instant::now();
-                    let resp = client.request(req).await;
-                    let done = Instant::now();
-
-                    let status = resp
-                        .map(|r| r.status().as_u16())
-                        .map_err(|e| Error::Unexpected(e.into()));
-
-                    let sample = Sample {
-                        target: target_url,
-                        due: sig.due,
-                        sent,
-                        done,
-                        status,
-                    };
+                    // Hold the in-flight permit (if any) until the samples have been sent.
+                    let _permit = permit;
 
-                    tx.send(sample).await?;
-
-                    Result::<(), anyhow::Error>::Ok(())
+                    requester.perform(work, record, sig.due, &tx).await
                 });
             }
         });
+    }
+
+    /// Runs the number of virtual users dictated by the user plan where each user waits for
+    /// a response before sending its next request.
+    fn run_closed_loop(
+        &self,
+        requester: Requester,
+        tx: mpsc::Sender<Event>,
+        stop: watch::Receiver<bool>,
+    ) {
+        let plan = UserPlan::new(&self.config.users);
+        let think_time = self.config.think_time;
+        let (users_tx, users_rx) = watch::channel(0);
+
+        tokio::spawn(async move {
+            let start = Instant::now();
+            let mut spawned = 0;
 
-        self.build_report(rx).await
+            // Adjust the number of active users as we progress through the plan. Dropping
+            // `users_tx` when the plan completes signals the users to stop.
+            while let Some(users) = plan.users_at(start.elapsed()) {
+                if tx.is_closed() || requester.is_exhausted() || *stop.borrow() {
+                    break;
+                }
+
+                users_tx.send_replace(users);
+
+                while spawned < users {
+                    let user = VirtualUser {
+                        id: spawned,
+                        requester: requester.clone(),
+                        think_time,
+                        active: users_rx.clone(),
+                        tx: tx.clone(),
+                    };
+
+                    tokio::spawn(user.run());
+                    spawned += 1;
+                }
+
+                tokio::time::sleep(USER_PLAN_RESOLUTION).await;
+            }
+        });
     }
 
-    async fn drain_receiver(mut rx: mpsc::Receiver<Sample>) {
+    async fn drain_receiver(mut rx: mpsc::Receiver<Event>) {
         rx.close();
         while (rx.recv().await).is_some() {}
     }
 
-    async fn build_report(&self, mut rx: mpsc::Receiver<Sample>) -> Result<Report, Error> {
+    async fn build_report(
+        &self,
+        mut rx: mpsc::Receiver<Event>,
+        interim_rx: &mut mpsc::Receiver<oneshot::Sender<InterimReport>>,
+    ) -> Result<Report, Error> {
         let mut report_builder = report::Builder::new(self.config.no_latency_correction);
 
+        // Samples are also recorded by a second builder that is reset each time an interim
+        // report is produced, so that interim reports cover the last interval too.
+        let mut interval_builder = report::Builder::new(self.config.no_latency_correction);
+
+        // Once stopped, in-flight requests are only waited on until the drain deadline (if any).
+        let mut stop = self.stop.subscribe();
+        let mut stopped = false;
+        let mut drain_deadline = None;
+
         let mut backend = metrics::Backend {};
-        while let Some(sample) = rx.recv().await {
+        loop {
+            // Check for the stop first so that the run is marked as interrupted even if every
+            // sender hangs up as a result.
+            let event = tokio::select! {
+                biased;
+                _ = stop.wait_for(|&stop| stop), if !stopped => {
+                    stopped = true;
+                    report_builder.mark_interrupted();
+                    drain_deadline = self.config.drain_timeout.map(|t| Instant::now() + t);
+                    continue;
+                }
+                Some(reply) = interim_rx.recv() => {
+                    let interval = report::Builder::new(self.config.no_latency_correction);
+                    let report = InterimReport {
+                        cumulative: report_builder.snapshot(),
+                        interval: mem::replace(&mut interval_builder, interval).build(),
+                    };
+
+                    // The requester may have given up waiting.
+                    let _ = reply.send(report);
+                    continue;
+                }
+                event = with_deadline(drain_deadline, rx.recv()) => match event {
+                    Some(Some(event)) => event,
+                    // Either every sender has hung up or the drain deadline has passed.
+                    Some(None) | None => break,
+                },
+            };
+
+            let sample = match event {
+                Event::Sample(sample) => sample,
+                Event::Dropped(dropped) => {
+                    report_builder.record_dropped(&dropped);
+                    interval_builder.record_dropped(&dropped);
+                    continue;
+                }
+                Event::Scenario(scenario) => {
+                    report_builder.record_scenario(&scenario)?;
+                    interval_builder.record_scenario(&scenario)?;
+                    continue;
+                }
+            };
+
             backend.record(&sample).await?;
             report_builder.record(&sample)?;
+            interval_builder.record(&sample)?;
 
             if self.config.stop_on_client_error {
                 if let Err(err) = sample.status {
@@ -154,22 +416,489 @@ impl Profiler {
                     });
                 }
             }
+
+            if self.config.stop_on_assertion_failure
+                && let Some(assertion) = sample.failed_assertions.first()
+            {
+                Self::drain_receiver(rx).await;
+                return Err(Error::AssertionFailed {
+                    assertion: assertion.clone(),
+                    report: report_builder.build(),
+                });
+            }
         }
 
         Ok(report_builder.build())
     }
 }
 
+/// Outcome of a timing signal that is sent from the request tasks to the report builder.
+#[derive(Debug)]
+pub enum Event {
+    /// A request was sent and its result sampled.
+    Sample(Sample),
+    /// A request (or scenario iteration) was not sent because the in-flight limit had been
+    /// reached.
+    Dropped(Dropped),
+    /// A scenario iteration completed or was aborted.
+    Scenario(ScenarioSample),
+}
+
+#[derive(Debug)]
+pub struct Dropped {
+    pub name: Option<String>,
+    pub target: Option<String>,
+}
+
+/// Work that is performed on each plan tick.
+enum Workload {
+    /// Independent requests whose targets are chosen by the selector.
+    Requests {
+        requests: Vec<Request>,
+        selector: Selector,
+    },
+    /// Iterations of a scenario. Each step is paired with the extractors that are applied to
+    /// its response.
+    Scenario {
+        name: String,
+        steps: Vec<(Request, Vec<Extractor>)>,
+    },
+}
+
+/// Unit of work started by a plan tick.
+enum Work {
+    /// Send a request to the selected target.
+    Request(Selection),
+    /// Run an iteration of the scenario.
+    Scenario,
+}
+
+/// Response headers and body that values are extracted from.
+struct Captured {
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+/// Returns an error if the request references a variable that is not available, so that we
+/// fail fast rather than failing every request.
+fn check_variables(request: &Request, vars: &HashSet<&str>) -> anyhow::Result<()> {
+    if let Some(var) = request.variables().find(|v| !vars.contains(v)) {
+        bail!(
+            "Request {} references undefined variable: {}",
+            request.url,
+            var
+        );
+    }
+
+    Ok(())
+}
+
+/// Error returned once the feeder's records have been exhausted.
+struct FeederExhausted;
+
+/// Sends requests to the test targets.
+#[derive(Clone)]
+struct Requester {
+    client: Client,
+    workload: Arc<Workload>,
+    /// How response bodies are read.
+    body: ResponseBody,
+    /// Sequence number of the next request.
+    seq: Arc<AtomicU64>,
+    /// Feeder that provides a record for each request (if any).
+    feeder: Option<Arc<Feeder>>,
+    timeout: Option<Duration>,
+}
+
+impl Requester {
+    /// Returns the next unit of work, selecting the target using the configured selection
+    /// strategy if the workload consists of independent requests.
+    fn next_work(&self) -> Work {
+        match &*self.workload {
+            Workload::Requests { selector, .. } => Work::Request(selector.select()),
+            Workload::Scenario { .. } => Work::Scenario,
+        }
+    }
+
+    /// Describes work that was dropped rather than performed.
+    fn dropped(&self, work: &Work) -> Dropped {
+        match (work, &*self.workload) {
+            (Work::Request(target), Workload::Requests { requests, .. }) => {
+                let request = &requests[target.index()];
+                Dropped {
+                    name: request.name.clone(),
+                    target: Some(request.url.to_string()),
+                }
+            }
+            (Work::Scenario, Workload::Scenario { name, .. }) => Dropped {
+                name: Some(name.clone()),
+                target: None,
+            },
+            _ => unreachable!("Work does not match the workload"),
+        }
+    }
+
+    /// Returns the feeder record to bind to the next request (if a feeder is in use).
+    ///
+    /// Returns an error once the feeder's records have been exhausted, at which point no
+    /// further requests should be sent.
+    fn next_record(&self) -> Result<Option<Arc<Variables>>, FeederExhausted> {
+        match &self.feeder {
+            Some(feeder) => feeder.next().map(Some).ok_or(FeederExhausted),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns whether the feeder's records have been exhausted.
+    fn is_exhausted(&self) -> bool {
+        self.feeder.as_ref().is_some_and(|f| f.is_exhausted())
+    }
+
+    /// Performs the unit of work and sends the resulting samples to the report builder.
+    ///
+    /// Returns an error if the report builder has hung up.
+    async fn perform(
+        &self,
+        work: Work,
+        record: Option<Arc<Variables>>,
+        due: Instant,
+        tx: &mpsc::Sender<Event>,
+    ) -> anyhow::Result<()> {
+        match (work, &*self.workload) {
+            (Work::Request(target), Workload::Requests { requests, .. }) => {
+                // The target selection is held until the request completes so that the target
+                // is considered to have an outstanding request while it is in flight.
+                let request = &requests[target.index()];
+                let (sample, _) = self.send(request, record.as_deref(), due, false).await;
+                drop(target);
+
+                tx.send(Event::Sample(sample)).await?;
+            }
+            (Work::Scenario, Workload::Scenario { name, steps }) => {
+                self.run_scenario(name, steps, record, due, tx).await?;
+            }
+            _ => unreachable!("Work does not match the workload"),
+        }
+
+        Ok(())
+    }
+
+    /// Runs an iteration of the scenario, sending a sample for each step followed by a sample
+    /// for the iteration as a whole.
+    ///
+    /// Each step is due as soon as the step before it has completed. The iteration is
+    /// aborted if a step fails, its response fails an assertion or a value cannot be
+    /// extracted from its response.
+    async fn run_scenario(
+        &self,
+        name: &str,
+        steps: &[(Request, Vec<Extractor>)],
+        record: Option<Arc<Variables>>,
+        due: Instant,
+        tx: &mpsc::Sender<Event>,
+    ) -> anyhow::Result<()> {
+        let mut vars = record.map(|r| (*r).clone()).unwrap_or_default();
+        let mut sent = None;
+        let mut step_due = due;
+        let mut aborted_at = None;
+
+        for (request, extractors) in steps {
+            let capture = !extractors.is_empty();
+            let (mut sample, captured) = self.send(request, Some(&vars), step_due, capture).await;
+
+            if let Some(captured) = captured {
+                for extractor in extractors {
+                    match extractor.extract(&captured.headers, &captured.body) {
+                        Ok(value) => {
+                            vars.insert(extractor.var.clone(), value);
+                        }
+                        Err(err) => {
+                            sample.status = Err(ClientError::extraction(&extractor.var, &err));
+                            break;
+                        }
+                    }
+                }
+            }
+
+            sent.get_or_insert(sample.sent);
+            step_due = sample.done;
+
+            let failed = sample.status.is_err() || !sample.failed_assertions.is_empty();
+            if failed {
+                aborted_at = sample.name.clone();
+            }
+
+            tx.send(Event::Sample(sample)).await?;
+
+            if failed {
+                break;
+            }
+        }
+
+        let scenario = ScenarioSample {
+            name: name.to_owned(),
+            aborted_at,
+            due,
+            sent: sent.unwrap_or(due),
+            done: Instant::now(),
+        };
+
+        tx.send(Event::Scenario(scenario)).await?;
+
+        Ok(())
+    }
+
+    /// Sends a request and samples the result.
+    ///
+    /// If `capture` is set, the headers and body of a successful response are returned so
+    /// that values can be extracted from them. The response is checked against the request's
+    /// assertions (if any).
+    async fn send(
+        &self,
+        request: &Request,
+        vars: Option<&Variables>,
+        due: Instant,
+        capture: bool,
+    ) -> (Sample, Option<Captured>) {
+        let seq = self.seq.fetch_add(1, Ordering::Relaxed);
+
+        // Requests that cannot be built (e.g. because a placeholder rendered an invalid URL)
+        // are reported as client errors without being sent.
+        let req = match request.build(seq, vars) {
+            Ok(req) => req,
+            Err(err) => {
+                let now = Instant::now();
+                let status = Err(ClientError::invalid_request(&err));
+                return (Sample::unsent(request, due, now, status), None);
+            }
+        };
+
+        // Request bodies are always built in full so their size is known up front.
+        let request_bytes = req.body().size_hint().exact().unwrap_or_default() as usize;
+
+        // The timeout also covers the time spent waiting for a connection to become free.
+        let acquiring = Instant::now();
+        let deadline = self.timeout.map(|t| acquiring + t);
+        let Some(conn) = with_deadline(deadline, self.client.acquire()).await else {
+            let status = Err(ClientError::timeout(self.timeout.unwrap_or_default()));
+            return (Sample::unsent(request, due, acquiring, status), None);
+        };
+
+        let sent = Instant::now();
+        let resp = with_deadline(deadline, conn.request(req)).await;
+        let headers = Instant::now();
+        let mut done = headers;
+
+        // The body is also needed to check the response against the request's assertions.
+        let buffer = capture || request.has_assertions() || self.body == ResponseBody::Buffer;
+
+        let mut address = None;
+        let mut proxied = false;
+        let mut version = None;
+        let mut captured = None;
+        let mut failed_assertions = vec![];
+        let mut phases = None;
+        let mut response_bytes = None;
+        let status = match resp {
+            Some(Ok(resp)) => {
+                let status = resp.status().as_u16();
+                let (parts, mut body) = resp.into_parts();
+                version = Some(parts.version);
+                proxied = parts.extensions.get::<Proxied>().is_some();
+
+                // The remote address of a proxied connection is the proxy's address.
+                if !proxied {
+                    address = parts.extensions.get::<HttpInfo>().map(|i| i.remote_addr().ip());
+                }
+
+                // Only the response to the request that had to wait for the connection to be
+                // established accounts for the time taken to establish it.
+                let timings = parts
+                    .extensions
+                    .get::<ConnectTimings>()
+                    .and_then(|t| t.claim());
+
+                // Read the response body, which also allows the connection to be reused.
+                // Reading is abandoned if the deadline passes as the connection is unlikely to
+                // be usable.
+                let mut buf = vec![];
+                let mut received = 0;
+                let read = with_deadline(deadline, async {
+                    while let Some(chunk) = body.data().await {
+                        let chunk = chunk?;
+                        received += chunk.len();
+                        if buffer {
+                            buf.extend_from_slice(&chunk);
+                        }
+                    }
+
+                    Ok::<_, hyper::Error>(())
+                })
+                .await;
+
+                let read_at = Instant::now();
+                if self.body != ResponseBody::Headers {
+                    done = read_at;
+                }
+
+                phases = Some(Phases::new(timings, sent, headers, read_at));
+                response_bytes = Some(received);
+
+                match read {
+                    Some(Err(err)) => Err(ClientError::body_read(&err)),
+                    // The request timed out even if its latency is only measured until the
+                    // response headers arrived.
+                    None => {
+                        done = read_at;
+                        Err(ClientError::timeout(self.timeout.unwrap_or_default()))
+                    }
+                    Some(Ok(())) => {
+                        failed_assertions = request.failed_assertions(status, &buf);
+                        if capture {
+                            captured = Some(Captured {
+                                headers: parts.headers,
+                                body: buf,
+                            });
+                        }
+
+                        Ok(status)
+                    }
+                }
+            }
+            Some(Err(err)) => Err(err.into()),
+            None => Err(ClientError::timeout(self.timeout.unwrap_or_default())),
+        };
+
+        let sample = Sample {
+            name: request.name.clone(),
+            target: request.url.to_string(),
+            connection: Some(conn.id()),
+            address,
+            proxied,
+            version,
+            due,
+            sent,
+            done,
+            status,
+            failed_assertions,
+            phases,
+            request_bytes,
+            response_bytes,
+        };
+
+        (sample, captured)
+    }
+}
+
+/// Runs the future to completion or until the deadline (if any) has passed.
+///
+/// Returns `None` if the deadline passed before the future completed.
+async fn with_deadline<F: Future>(deadline: Option<Instant>, fut: F) -> Option<F::Output> {
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline.into(), fut).await.ok(),
+        None => Some(fut.await),
+    }
+}
+
+/// A closed-loop virtual user.
+struct VirtualUser {
+    /// Identifies the user. Users are only active while their ID is below the number of
+    /// users that the plan currently calls for.
+    id: usize,
+    requester: Requester,
+    think_time: Option<ThinkTime>,
+    active: watch::Receiver<usize>,
+    tx: mpsc::Sender<Event>,
+}
+
+impl VirtualUser {
+    async fn run(mut self) {
+        loop {
+            // Stop when the plan has completed.
+            if self.active.has_changed().is_err() {
+                break;
+            }
+
+            // Wait until the plan calls for this user to be active.
+            if *self.active.borrow_and_update() <= self.id {
+                if self.active.changed().await.is_err() {
+                    break;
+                }
+
+                continue;
+            }
+
+            // Stop once the feeder's records have been exhausted.
+            let Ok(record) = self.requester.next_record() else {
+                break;
+            };
+
+            // In a closed loop work is due as soon as the user is ready to perform it.
+            let work = self.requester.next_work();
+            let due = Instant::now();
+            let performed = self.requester.perform(work, record, due, &self.tx).await;
+            if performed.is_err() {
+                break;
+            }
+
+            if let Some(think_time) = &self.think_time {
+                let think = think_time.sample(&mut rand::thread_rng());
+                tokio::time::sleep(think).await;
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Sample {
-    pub target: Url,
+    pub name: Option<String>,
+    pub target: String,
+    pub connection: Option<usize>,
+    /// IP address of the server that responded (if a response was received).
+    pub address: Option<IpAddr>,
+    /// Whether the response was received over a connection tunnelled through a proxy.
+    pub proxied: bool,
+    pub version: Option<hyper::Version>,
     pub due: Instant,
     pub sent: Instant,
     pub done: Instant,
-    pub status: Result<u16, Error>,
+    pub status: Result<u16, ClientError>,
+    /// Names of the assertions that the response failed.
+    pub failed_assertions: Vec<String>,
+    /// Time taken by each phase of the request (if a response was received).
+    pub phases: Option<Phases>,
+    /// Size of the request body in bytes.
+    pub request_bytes: usize,
+    /// Number of response body bytes received (if a response was received).
+    pub response_bytes: Option<usize>,
 }
 
 impl Sample {
+    /// Creates a sample for a request that was not sent (e.g. because it could not be built).
+    fn unsent(
+        request: &Request,
+        due: Instant,
+        sent: Instant,
+        status: Result<u16, ClientError>,
+    ) -> Self {
+        Self {
+            name: request.name.clone(),
+            target: request.url.to_string(),
+            connection: None,
+            address: None,
+            proxied: false,
+            version: None,
+            due,
+            sent,
+            done: Instant::now(),
+            status,
+            failed_assertions: vec![],
+            phases: None,
+            request_bytes: 0,
+            response_bytes: None,
+        }
+    }
+
     pub fn actual_latency(&self) -> Duration {
         self.done - self.sent
     }
@@ -182,3 +911,515 @@ impl Sample {
         self.sent - self.due
     }
 }
+
+/// Time taken by each phase of a request.
+#[derive(Debug)]
+pub struct Phases {
+    /// Time taken to resolve the host name (if a connection had to be established and the
+    /// host was not an IP address).
+    pub dns: Option<Duration>,
+    /// Time taken to establish the TCP connection (if one had to be established).
+    pub connect: Option<Duration>,
+    /// Time taken by the TLS handshake (if a TLS connection had to be established).
+    pub tls: Option<Duration>,
+    /// Time to first byte: the time between the request being sent over an established
+    /// connection and the response headers being received.
+    pub ttfb: Duration,
+    /// Time taken to read the response body.
+    pub body: Duration,
+}
+
+/// Phase of a request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Dns,
+    Connect,
+    Tls,
+    Ttfb,
+    Body,
+}
+
+impl Phases {
+    /// Creates the phases of a request.
+    ///
+    /// # Arguments
+    ///
+    /// * `timings` - Timings of the connection if it had to be established for the request
+    /// * `sent` - When the request was sent
+    /// * `headers` - When the response headers were received
+    /// * `body` - When the response body had been read
+    fn new(
+        timings: Option<&ConnectTimings>,
+        sent: Instant,
+        headers: Instant,
+        body: Instant,
+    ) -> Self {
+        let established = timings.map_or(sent, |t| t.established.max(sent));
+
+        Self {
+            dns: timings.and_then(|t| t.dns),
+            connect: timings.map(|t| t.connect),
+            tls: timings.and_then(|t| t.tls),
+            ttfb: headers.saturating_duration_since(established),
+            body: body - headers,
+        }
+    }
+
+    /// Returns the duration of each phase that the request went through.
+    pub fn iter(&self) -> impl Iterator<Item = (Phase, Duration)> {
+        [
+            (Phase::Dns, self.dns),
+            (Phase::Connect, self.connect),
+            (Phase::Tls, self.tls),
+            (Phase::Ttfb, Some(self.ttfb)),
+            (Phase::Body, Some(self.body)),
+        ]
+        .into_iter()
+        .filter_map(|(phase, d)| d.map(|d| (phase, d)))
+    }
+}
+
+/// End-to-end result of a scenario iteration.
+#[derive(Debug)]
+pub struct ScenarioSample {
+    pub name: String,
+    /// Name of the step at which the iteration was aborted (if it was).
+    pub aborted_at: Option<String>,
+    pub due: Instant,
+    /// When the first step was sent.
+    pub sent: Instant,
+    pub done: Instant,
+}
+
+impl ScenarioSample {
+    pub fn actual_latency(&self) -> Duration {
+        self.done - self.sent
+    }
+
+    pub fn corrected_latency(&self) -> Duration {
+        self.done - self.due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::Infallible, net::SocketAddr, sync::Mutex as StdMutex};
+
+    use hyper::{
+        server::conn::{AddrStream, Http},
+        service::{make_service_fn, service_fn},
+        Body, Response,
+    };
+    use metron::{Header, HttpProtocol};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::profile::{
+        client::ClientErrorKind, PlanSegment, ProxyConfig, SignallerKind, Target,
+    };
+
+    /// Value of the `x-test` header of each request received by a test server, along with
+    /// the port of the connection that it was received on.
+    type Received = Arc<StdMutex<Vec<(Option<String>, u16)>>>;
+
+    /// Starts a server that responds to every request, returning its URL.
+    ///
+    /// # Arguments
+    ///
+    /// * `delay` - How long the server waits before sending the response headers
+    /// * `body_delay` - How long the server then waits before sending the response body
+    async fn serve(delay: Duration, body_delay: Duration) -> (String, Received) {
+        serve_on(([127, 0, 0, 1], 0).into(), delay, body_delay).await
+    }
+
+    /// Starts a server on the address that responds to every request, returning its URL.
+    async fn serve_on(
+        addr: SocketAddr,
+        delay: Duration,
+        body_delay: Duration,
+    ) -> (String, Received) {
+        let received = Received::default();
+        let make_service = make_service_fn({
+            let received = received.clone();
+            move |conn: &AddrStream| {
+                let port = conn.remote_addr().port();
+                let received = received.clone();
+                let service = service_fn(move |req: hyper::Request<Body>| {
+                    let header = req.headers().get("x-test");
+                    let header = header.and_then(|v| v.to_str().ok()).map(String::from);
+                    received.lock().unwrap().push((header, port));
+
+                    async move {
+                        tokio::time::sleep(delay).await;
+                        let (mut sender, body) = Body::channel();
+                        tokio::spawn(async move {
+                            tokio::time::sleep(body_delay).await;
+                            let _ = sender.send_data("ok".into()).await;
+                        });
+
+                        Ok::<_, Infallible>(Response::new(body))
+                    }
+                });
+
+                async move { Ok::<_, Infallible>(service) }
+            }
+        });
+
+        // The response headers and body are written separately, which must not wait for
+        // delayed ACKs when relayed by a proxy.
+        let server = hyper::Server::bind(&addr)
+            .tcp_nodelay(true)
+            .serve(make_service);
+        let url = format!("http://{}/", server.local_addr());
+        tokio::spawn(server);
+
+        (url, received)
+    }
+
+    /// Returns the config of a short fixed rate test against the target.
+    fn fixed_rate(target: &str) -> Config {
+        Config {
+            segments: vec![PlanSegment::Fixed {
+                rate: "100".parse().unwrap(),
+                duration: Some(Duration::from_millis(500)),
+            }],
+            connections: 1,
+            targets: vec![Target::new(target.parse().unwrap())],
+            signaller_kind: SignallerKind::Cooperative,
+            ..Config::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn headers_and_connections() {
+        let (url, received) = serve(Duration::ZERO, Duration::ZERO).await;
+        let mut config = fixed_rate(&url);
+        config.connections = 4;
+        config.headers = vec![Header {
+            name: "x-test".to_owned(),
+            value: "metron".to_owned(),
+        }];
+
+        let report = Profiler::new(config).run().await.unwrap();
+        let received = received.lock().unwrap();
+        assert!(report.total_requests >= 40);
+        assert_eq!(received.len(), report.total_requests);
+
+        // Every request carries the configured header.
+        assert!(received.iter().all(|(h, _)| h.as_deref() == Some("metron")));
+
+        // Requests are spread evenly over exactly the configured number of connections.
+        let ports: HashSet<_> = received.iter().map(|(_, port)| port).collect();
+        assert_eq!(ports.len(), 4);
+        assert_eq!(report.connections.len(), 4);
+        for conn in &report.connections {
+            assert!(conn.total_requests.abs_diff(report.total_requests / 4) <= 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn offered_http2_keeps_one_connection_per_slot() {
+        // HTTP/2 cannot be negotiated without TLS so the slow server is sent concurrent
+        // requests that would each need their own HTTP/1.1 connection.
+        let (url, received) = serve(Duration::from_millis(50), Duration::ZERO).await;
+        let mut config = fixed_rate(&url);
+        config.connections = 2;
+        config.http_protocol = HttpProtocol::Http2;
+
+        let report = Profiler::new(config).run().await.unwrap();
+        let received = received.lock().unwrap();
+        let ports: HashSet<_> = received.iter().map(|(_, port)| port).collect();
+        assert!(report.total_requests >= 40);
+        assert_eq!(ports.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn phases_fit_within_latency() {
+        // The host name is resolved so that every connection phase is timed.
+        let (url, _) = serve(Duration::from_millis(10), Duration::ZERO).await;
+        let url = url.replace("127.0.0.1", "localhost");
+        let client = Client::new(&fixed_rate(&url)).unwrap();
+
+        for first in [true, false] {
+            let conn = client.acquire().await;
+            let req = hyper::Request::get(&url).body(Body::empty()).unwrap();
+            let sent = Instant::now();
+            let (parts, body) = conn.request(req).await.unwrap().into_parts();
+            let headers = Instant::now();
+            hyper::body::to_bytes(body).await.unwrap();
+            let done = Instant::now();
+
+            // Only the first response waited for the connection to be established.
+            let timings = parts.extensions.get::<ConnectTimings>();
+            let timings = timings.and_then(|t| t.claim());
+            assert_eq!(timings.is_some(), first);
+            assert!(timings.is_none_or(|t| t.dns.is_some()));
+
+            let phases = Phases::new(timings, sent, headers, done);
+            let total: Duration = phases.iter().map(|(_, d)| d).sum();
+            assert!(total <= done - sent, "{:?} > {:?}", total, done - sent);
+        }
+    }
+
+    #[tokio::test]
+    async fn interim_report_during_run() {
+        let (url, _) = serve(Duration::ZERO, Duration::ZERO).await;
+        let profiler = Arc::new(Profiler::new(fixed_rate(&url)));
+        let run = tokio::spawn({
+            let profiler = profiler.clone();
+            async move { profiler.run().await }
+        });
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        let first = profiler.interim_report().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let second = profiler.interim_report().await.unwrap();
+        let report = run.await.unwrap().unwrap();
+
+        // Each interim report covers part of the run, which then continues to completion.
+        let partial = first.cumulative.total_requests;
+        assert!(partial > 0 && partial < report.total_requests);
+        assert!(!first.cumulative.interrupted && !report.interrupted);
+        assert!(report.total_requests >= 40);
+
+        // The interval only covers the requests since the previous interim report.
+        let cumulative = second.cumulative.total_requests;
+        assert!(cumulative > partial);
+        assert_eq!(second.interval.total_requests, cumulative - partial);
+    }
+
+    #[tokio::test]
+    async fn resolve_override_to_several_addresses() {
+        // The host name resolves to two servers listening on the same port of different
+        // loopback addresses.
+        let first: SocketAddr = ([127, 0, 0, 1], 0).into();
+        let (url, first_received) = serve_on(first, Duration::ZERO, Duration::ZERO).await;
+        let port = url.parse::<hyper::Uri>().unwrap().port_u16().unwrap();
+        let second = ([127, 0, 0, 2], port).into();
+        let (_, second_received) = serve_on(second, Duration::ZERO, Duration::ZERO).await;
+
+        let mut config = fixed_rate(&format!("http://metron.test:{}/", port));
+        config.connections = 2;
+        config.resolve = vec![format!("metron.test:{}:127.0.0.1,127.0.0.2", port)
+            .parse()
+            .unwrap()];
+        config.spread_addresses = true;
+
+        // Each connection is established to a different one of the servers.
+        let report = Profiler::new(config).run().await.unwrap();
+        assert!(report.total_requests >= 40);
+        assert!(!first_received.lock().unwrap().is_empty());
+        assert!(!second_received.lock().unwrap().is_empty());
+
+        let addresses: HashSet<_> = report
+            .address_latency
+            .iter()
+            .filter_map(|s| s.address)
+            .collect();
+        let expected = HashSet::from([[127, 0, 0, 1].into(), [127, 0, 0, 2].into()]);
+        assert_eq!(addresses, expected);
+    }
+
+    #[tokio::test]
+    async fn unix_socket_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("app.sock");
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+        let received = Arc::new(StdMutex::new(vec![]));
+        tokio::spawn({
+            let received = received.clone();
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let received = received.clone();
+                    let service = service_fn(move |req: hyper::Request<Body>| {
+                        received.lock().unwrap().push(req.uri().path().to_owned());
+                        async { Ok::<_, Infallible>(Response::new(Body::from("ok"))) }
+                    });
+
+                    tokio::spawn(Http::new().serve_connection(stream, service));
+                }
+            }
+        });
+
+        let url = format!("unix://{}:/health", socket.display());
+        let report = Profiler::new(fixed_rate(&url)).run().await.unwrap();
+        let received = received.lock().unwrap();
+        assert!(report.total_requests >= 40);
+        assert_eq!(received.len(), report.total_requests);
+        assert!(received.iter().all(|path| path == "/health"));
+
+        // The responses are reported against the Unix socket URL.
+        let targets: HashSet<_> = report
+            .response_latency
+            .iter()
+            .map(|s| s.target.as_deref())
+            .collect();
+        assert_eq!(targets, HashSet::from([Some(url.as_str())]));
+    }
+
+    /// Starts a SOCKS5 proxy that tunnels every connection to the server (whatever address
+    /// it is asked to connect to), returning its address along with the address type of each
+    /// CONNECT request that it receives.
+    async fn socks5_proxy(server: SocketAddr) -> (SocketAddr, Arc<StdMutex<Vec<u8>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let address_types = Arc::new(StdMutex::new(vec![]));
+        tokio::spawn({
+            let address_types = address_types.clone();
+            async move {
+                while let Ok((mut client, _)) = listener.accept().await {
+                    let mut greeting = [0; 3];
+                    client.read_exact(&mut greeting).await.unwrap();
+                    client.write_all(&[5, 0]).await.unwrap();
+
+                    let mut request = [0; 4];
+                    client.read_exact(&mut request).await.unwrap();
+                    address_types.lock().unwrap().push(request[3]);
+                    let len = match request[3] {
+                        1 => 4,
+                        4 => 16,
+                        _ => client.read_u8().await.unwrap() as usize,
+                    };
+                    let mut dst = vec![0; len + 2];
+                    client.read_exact(&mut dst).await.unwrap();
+
+                    // Relay the small writes immediately (see `serve_on`).
+                    let mut server = tokio::net::TcpStream::connect(server).await.unwrap();
+                    client.set_nodelay(true).unwrap();
+                    server.set_nodelay(true).unwrap();
+                    let reply = [5, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+                    client.write_all(&reply).await.unwrap();
+                    tokio::spawn(async move {
+                        let _ = tokio::io::copy_bidirectional(&mut client, &mut server).await;
+                    });
+                }
+            }
+        });
+
+        (addr, address_types)
+    }
+
+    #[tokio::test]
+    async fn socks5_proxy_tunnels() {
+        let (url, _) = serve(Duration::ZERO, Duration::ZERO).await;
+        let server = url.parse::<hyper::Uri>().unwrap();
+        let server: SocketAddr = server.authority().unwrap().as_str().parse().unwrap();
+        let (proxy, address_types) = socks5_proxy(server).await;
+
+        // The host name is only sent to socks5h proxies. Otherwise, it is resolved locally
+        // (here using an override) and the proxy is sent the IPv4 address.
+        for (scheme, address_type) in [("socks5", 1), ("socks5h", 3)] {
+            let port = server.port();
+            let mut config = fixed_rate(&format!("http://metron.test:{}/", port));
+            config.resolve = vec![format!("metron.test:{}:127.0.0.1", port).parse().unwrap()];
+            config.proxy = Some(ProxyConfig {
+                url: format!("{}://{}", scheme, proxy).parse().unwrap(),
+                no_proxy: vec![],
+            });
+
+            let report = Profiler::new(config).run().await.unwrap();
+            assert!(report.total_requests >= 40);
+            assert_eq!(report.total_proxied, report.total_requests);
+            let address_types = std::mem::take(&mut *address_types.lock().unwrap());
+            assert_eq!(address_types, [address_type]);
+        }
+    }
+
+    #[tokio::test]
+    async fn response_size_per_status() {
+        let (url, _) = serve(Duration::ZERO, Duration::ZERO).await;
+        let report = Profiler::new(fixed_rate(&url)).run().await.unwrap();
+        assert!(report.total_requests >= 40);
+
+        // Every response body is "ok", which is all received within the run.
+        let [size] = &report.response_size[..] else {
+            panic!("Expected a single response size section");
+        };
+        assert_eq!(size.status_code, 200);
+        assert!(size.percentiles.iter().all(|p| p.bytes == 2));
+        assert_eq!(size.total_bytes, 2 * report.total_requests);
+        assert_eq!(size.bytes_per_sec, report.response_bytes_per_sec);
+    }
+
+    /// Returns the number of requests that timed out and the longest (corrected) latency
+    /// of those requests.
+    fn timeouts(report: &Report) -> (usize, Duration) {
+        let sections = &report.timeout_latency;
+        let total = sections.iter().map(|s| s.total_requests).sum();
+        let longest = sections
+            .iter()
+            .flat_map(|s| s.percentiles.iter().map(|p| p.duration))
+            .max()
+            .unwrap_or_default();
+
+        (total, longest)
+    }
+
+    #[tokio::test]
+    async fn timeouts_are_counted() {
+        // The server is too slow for every request, which queue for the single connection.
+        let (url, _) = serve(Duration::from_millis(300), Duration::ZERO).await;
+        let mut config = fixed_rate(&url);
+        config.timeout = Some(Duration::from_millis(100));
+
+        let report = Profiler::new(config).run().await.unwrap();
+        let (total, longest) = timeouts(&report);
+        assert!(report.total_requests >= 40);
+        assert_eq!(total, report.total_requests);
+        assert!(report.response_latency.is_empty());
+
+        // Time spent waiting for the connection counts towards the timeout.
+        assert!(longest < Duration::from_millis(150), "{:?}", longest);
+
+        // The body is still subject to the timeout when latency is only measured until the
+        // response headers arrive.
+        let (url, _) = serve(Duration::ZERO, Duration::from_millis(300)).await;
+        let mut config = fixed_rate(&url);
+        config.connections = 10;
+        config.timeout = Some(Duration::from_millis(100));
+        config.response_body = ResponseBody::Headers;
+
+        let report = Profiler::new(config).run().await.unwrap();
+        let (total, _) = timeouts(&report);
+        assert!(report.total_requests >= 40);
+        assert_eq!(total, report.total_requests);
+        assert!(report.response_latency.is_empty());
+    }
+
+    /// Returns the error kinds reported for the failed requests.
+    fn error_kinds(report: &Report) -> HashSet<ClientErrorKind> {
+        report
+            .error_latency
+            .iter()
+            .chain(&report.timeout_latency)
+            .filter_map(|s| s.error_kind)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn error_kinds_are_classified() {
+        // Nothing listens on the port once the listener is dropped.
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let config = fixed_rate(&format!("http://127.0.0.1:{}/", port));
+        let report = Profiler::new(config).run().await.unwrap();
+        let expected = HashSet::from([ClientErrorKind::ConnectionRefused]);
+        assert_eq!(error_kinds(&report), expected);
+
+        // The reserved `.invalid` top level domain never resolves.
+        let config = fixed_rate("http://metron.invalid/");
+        let report = Profiler::new(config).run().await.unwrap();
+        assert_eq!(error_kinds(&report), HashSet::from([ClientErrorKind::Dns]));
+
+        let (url, _) = serve(Duration::from_millis(300), Duration::ZERO).await;
+        let mut config = fixed_rate(&url);
+        config.timeout = Some(Duration::from_millis(100));
+        let report = Profiler::new(config).run().await.unwrap();
+        let expected = HashSet::from([ClientErrorKind::Timeout]);
+        assert_eq!(error_kinds(&report), expected);
+    }
+}
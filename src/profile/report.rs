@@ -1,13 +1,16 @@
 use std::{
     collections::HashMap,
+    net::IpAddr,
     time::{Duration, Instant},
 };
 
 use anyhow::Result;
 use serde::Serialize;
-use url::Url;
 
-use super::profiler::Sample;
+use super::{
+    client::ClientErrorKind,
+    profiler::{Dropped, Phase, Sample, ScenarioSample},
+};
 
 const STANDARD_PERCENTILES: [f64; 6] = [99.9, 99.0, 95.0, 90.0, 75.0, 50.0];
 
@@ -15,22 +18,84 @@ const STANDARD_PERCENTILES: [f64; 6] = [99.9, 99.0, 95.0, 90.0, 75.0, 50.0];
 pub struct Report {
     pub response_latency: Vec<ReportSection>,
     pub error_latency: Vec<ReportSection>,
+    pub timeout_latency: Vec<ReportSection>,
     pub request_delay: Vec<ReportSection>,
+    pub phase_latency: Vec<ReportSection>,
+    pub address_latency: Vec<ReportSection>,
+    pub response_size: Vec<ReportSize>,
+    pub dropped_requests: Vec<ReportSection>,
+    /// Number of responses received over connections tunnelled through a proxy.
+    pub proxied_requests: Vec<ReportSection>,
+    pub scenario_latency: Vec<ReportSection>,
+    pub aborted_scenarios: Vec<ReportSection>,
+    pub failed_assertions: Vec<ReportAssertion>,
+    pub connections: Vec<ReportConnection>,
+    pub protocols: Vec<ReportProtocol>,
     pub total_requests: usize,
+    pub total_dropped: usize,
+    pub total_proxied: usize,
     #[serde(with = "humantime_serde")]
     pub total_duration: Duration,
+    /// Achieved throughput in requests per second.
+    pub throughput: f64,
+    /// Total size of the request bodies sent.
+    pub total_request_bytes: usize,
+    /// Total size of the response bodies received.
+    pub total_response_bytes: usize,
+    /// Achieved request body throughput in bytes per second.
+    pub request_bytes_per_sec: f64,
+    /// Achieved response body throughput in bytes per second.
+    pub response_bytes_per_sec: f64,
+    /// Whether the run was stopped (e.g. by Ctrl-C) before the plan completed.
+    pub interrupted: bool,
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct ReportSection {
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub target: Option<Url>,
+    pub scenario: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<IpAddr>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phase: Option<Phase>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub status_code: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<ClientErrorKind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_sample: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     pub percentiles: Vec<ReportPercentile>,
     pub total_requests: usize,
 }
 
+impl ReportSection {
+    /// Creates a section with the latency percentiles of the histogram.
+    fn latency(request: Option<String>, target: Option<String>, hist: &Histogram) -> Self {
+        Self {
+            request,
+            target,
+            percentiles: percentiles(hist),
+            total_requests: hist.len() as usize,
+            ..Self::default()
+        }
+    }
+
+    /// Creates a section that only counts requests.
+    fn count(request: Option<String>, target: Option<String>, total_requests: usize) -> Self {
+        Self {
+            request,
+            target,
+            total_requests,
+            ..Self::default()
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize)]
 pub struct ReportPercentile {
     pub percentile: f64,
@@ -38,9 +103,75 @@ pub struct ReportPercentile {
     pub duration: Duration,
 }
 
+#[derive(Clone, Debug, Serialize)]
+pub struct ReportSize {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request: Option<String>,
+    pub target: String,
+    pub status_code: u16,
+    pub percentiles: Vec<ReportSizePercentile>,
+    pub total_requests: usize,
+    /// Total size of the response bodies received.
+    pub total_bytes: usize,
+    /// Achieved response body throughput in bytes per second.
+    pub bytes_per_sec: f64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ReportSizePercentile {
+    pub percentile: f64,
+    pub bytes: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ReportAssertion {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request: Option<String>,
+    pub assertion: String,
+    pub total_failures: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ReportConnection {
+    pub connection: usize,
+    pub total_requests: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ReportProtocol {
+    pub protocol: String,
+    pub total_requests: usize,
+}
+
+/// Snapshot of a report that is still being built.
+#[derive(Clone, Debug, Serialize)]
+pub struct InterimReport {
+    /// Report covering the run so far.
+    pub cumulative: Report,
+    /// Report covering the interval since the previous interim report (or the start of the
+    /// run if there is no previous interim report).
+    pub interval: Report,
+}
+
 type Histogram = hdrhistogram::Histogram<u64>;
 
+/// Returns the standard latency percentiles of the histogram.
+fn percentiles(hist: &Histogram) -> Vec<ReportPercentile> {
+    STANDARD_PERCENTILES
+        .iter()
+        .map(|&p| ReportPercentile {
+            percentile: p,
+            duration: Duration::from_micros(hist.value_at_percentile(p)),
+        })
+        .collect()
+}
+
+/// Identifies the target of a request by its request template name (if any) and URL. The
+/// URL is reported as configured (i.e. before any placeholders are evaluated).
+type TargetKey = (Option<String>, String);
+
 /// Builder used to construct a [Report].
+#[derive(Clone)]
 pub struct Builder {
     /// Whether latency correction is disabled.
     no_latency_correction: bool,
@@ -48,16 +179,71 @@ pub struct Builder {
     /// When we started building the report.
     start: Instant,
 
-    /// Response latency histograms keyed by target URL and HTTP status.
-    response_histograms: HashMap<(Url, u16), Histogram>,
+    /// Response latency histograms keyed by target and HTTP status.
+    response_histograms: HashMap<(TargetKey, u16), Histogram>,
 
-    /// Error latency histograms keyed by target URL.
-    error_histograms: HashMap<Url, Histogram>,
+    /// Error latency histograms keyed by target and error kind. Each histogram is paired
+    /// with the message of the first error of its kind to help diagnose the cause.
+    error_histograms: HashMap<(TargetKey, ClientErrorKind), (Histogram, String)>,
 
-    /// Request delay histograms keyed by target URL. These histograms track the delay period
+    /// Timeout latency histograms keyed by target. Timed out requests are tracked
+    /// separately from other errors.
+    timeout_histograms: HashMap<TargetKey, (Histogram, String)>,
+
+    /// Request delay histograms keyed by target. These histograms track the delay period
     /// between when a request should have been sent and when it was sent (i.e. when the delay
     /// increases it means that we cannot keep up with the desired request rate).
-    delay_histograms: HashMap<Url, Histogram>,
+    delay_histograms: HashMap<TargetKey, Histogram>,
+
+    /// Phase latency histograms keyed by target and request phase. Phase latencies are not
+    /// corrected as each phase only covers part of the request.
+    phase_histograms: HashMap<(TargetKey, Phase), Histogram>,
+
+    /// Response latency histograms keyed by target and the IP address of the server that
+    /// responded.
+    address_histograms: HashMap<(TargetKey, IpAddr), Histogram>,
+
+    /// Response body size histograms keyed by target and HTTP status.
+    size_histograms: HashMap<(TargetKey, u16), Histogram>,
+
+    /// Total size of the response bodies received keyed by target and HTTP status.
+    size_totals: HashMap<(TargetKey, u16), usize>,
+
+    /// Total size of the request bodies sent.
+    request_bytes: usize,
+
+    /// Total size of the response bodies received.
+    response_bytes: usize,
+
+    /// Number of requests that were dropped rather than sent keyed by request template name
+    /// and target (the target of a dropped scenario iteration is unknown). Dropped requests
+    /// are excluded from the latency histograms.
+    dropped_counts: HashMap<(Option<String>, Option<String>), usize>,
+
+    /// Number of responses received over connections tunnelled through a proxy keyed by
+    /// target.
+    proxied_counts: HashMap<TargetKey, usize>,
+
+    /// End-to-end latency histograms of the scenario iterations that completed all of their
+    /// steps keyed by scenario name.
+    scenario_histograms: HashMap<String, Histogram>,
+
+    /// Number of scenario iterations that were aborted keyed by scenario name and the name of
+    /// the step at which they were aborted.
+    aborted_counts: HashMap<(String, String), usize>,
+
+    /// Number of responses that failed each assertion keyed by request template name and
+    /// assertion name.
+    assertion_counts: HashMap<(Option<String>, String), usize>,
+
+    /// Number of requests sent over each connection keyed by connection ID.
+    connection_counts: HashMap<usize, usize>,
+
+    /// Number of responses received keyed by the negotiated HTTP protocol version.
+    protocol_counts: HashMap<String, usize>,
+
+    /// Whether the run was stopped before the plan completed.
+    interrupted: bool,
 }
 
 impl Builder {
@@ -67,19 +253,79 @@ impl Builder {
             start: Instant::now(),
             response_histograms: HashMap::new(),
             error_histograms: HashMap::new(),
+            timeout_histograms: HashMap::new(),
             delay_histograms: HashMap::new(),
+            phase_histograms: HashMap::new(),
+            address_histograms: HashMap::new(),
+            size_histograms: HashMap::new(),
+            size_totals: HashMap::new(),
+            request_bytes: 0,
+            response_bytes: 0,
+            dropped_counts: HashMap::new(),
+            proxied_counts: HashMap::new(),
+            scenario_histograms: HashMap::new(),
+            aborted_counts: HashMap::new(),
+            assertion_counts: HashMap::new(),
+            connection_counts: HashMap::new(),
+            protocol_counts: HashMap::new(),
+            interrupted: false,
         }
     }
 
-    pub fn record(&mut self, sample: &Sample) -> Result<()> {
-        let hist = if let Ok(status) = sample.status {
-            self.response_histograms
-                .entry((sample.target.clone(), status))
-                .or_insert_with(Self::new_histogram)
+    /// Marks the run as having been stopped before the plan completed.
+    pub fn mark_interrupted(&mut self) {
+        self.interrupted = true;
+    }
+
+    pub fn record_dropped(&mut self, dropped: &Dropped) {
+        let key = (dropped.name.clone(), dropped.target.clone());
+        *self.dropped_counts.entry(key).or_default() += 1;
+    }
+
+    pub fn record_scenario(&mut self, sample: &ScenarioSample) -> Result<()> {
+        if let Some(step) = &sample.aborted_at {
+            let key = (sample.name.clone(), step.clone());
+            *self.aborted_counts.entry(key).or_default() += 1;
+            return Ok(());
+        }
+
+        let hist = self
+            .scenario_histograms
+            .entry(sample.name.clone())
+            .or_insert_with(Self::new_histogram);
+
+        let latency = if self.no_latency_correction {
+            sample.actual_latency().as_micros().try_into()?
         } else {
-            self.error_histograms
-                .entry(sample.target.clone())
-                .or_insert_with(Self::new_histogram)
+            sample.corrected_latency().as_micros().try_into()?
+        };
+
+        hist.record(latency)?;
+
+        Ok(())
+    }
+
+    pub fn record(&mut self, sample: &Sample) -> Result<()> {
+        let key = (sample.name.clone(), sample.target.clone());
+        let hist = match &sample.status {
+            Ok(status) => self
+                .response_histograms
+                .entry((key.clone(), *status))
+                .or_insert_with(Self::new_histogram),
+            Err(err) if err.kind == ClientErrorKind::Timeout => {
+                &mut self
+                    .timeout_histograms
+                    .entry(key.clone())
+                    .or_insert_with(|| (Self::new_histogram(), err.message.clone()))
+                    .0
+            }
+            Err(err) => {
+                &mut self
+                    .error_histograms
+                    .entry((key.clone(), err.kind))
+                    .or_insert_with(|| (Self::new_histogram(), err.message.clone()))
+                    .0
+            }
         };
 
         let latency = if self.no_latency_correction {
@@ -92,77 +338,246 @@ impl Builder {
 
         let delay_histogram = self
             .delay_histograms
-            .entry(sample.target.clone())
+            .entry(key.clone())
             .or_insert_with(Self::new_histogram);
 
         let delay = sample.client_latency().as_micros().try_into()?;
         delay_histogram.record(delay)?;
 
+        if let Some(address) = sample.address && sample.status.is_ok() {
+            let address_histogram = self
+                .address_histograms
+                .entry((key.clone(), address))
+                .or_insert_with(Self::new_histogram);
+
+            address_histogram.record(latency)?;
+        }
+
+        for (phase, duration) in sample.phases.iter().flat_map(|p| p.iter()) {
+            let phase_histogram = self
+                .phase_histograms
+                .entry((key.clone(), phase))
+                .or_insert_with(Self::new_histogram);
+
+            phase_histogram.record(duration.as_micros().try_into()?)?;
+        }
+
+        self.request_bytes += sample.request_bytes;
+        if let Some(bytes) = sample.response_bytes {
+            self.response_bytes += bytes;
+
+            if let Ok(status) = sample.status {
+                let size_histogram = self
+                    .size_histograms
+                    .entry((key.clone(), status))
+                    .or_insert_with(Self::new_histogram);
+
+                size_histogram.record(bytes as u64)?;
+                *self.size_totals.entry((key.clone(), status)).or_default() += bytes;
+            }
+        }
+
+        for assertion in &sample.failed_assertions {
+            let key = (sample.name.clone(), assertion.clone());
+            *self.assertion_counts.entry(key).or_default() += 1;
+        }
+
+        if sample.proxied {
+            *self.proxied_counts.entry(key.clone()).or_default() += 1;
+        }
+
+        if let Some(connection) = sample.connection {
+            *self.connection_counts.entry(connection).or_default() += 1;
+        }
+
+        if let Some(version) = sample.version {
+            let protocol = format!("{:?}", version);
+            *self.protocol_counts.entry(protocol).or_default() += 1;
+        }
+
         Ok(())
     }
 
+    /// Builds a report from the samples recorded so far without consuming the builder.
+    pub fn snapshot(&self) -> Report {
+        self.clone().build()
+    }
+
     pub fn build(self) -> Report {
+        let total_duration = self.start.elapsed();
+
         let mut response_latency = vec![];
-        for ((url, status), hist) in self.response_histograms {
+        for (((name, url), status), hist) in self.response_histograms {
             response_latency.push(ReportSection {
-                target: Some(url.clone()),
                 status_code: Some(status),
-                percentiles: STANDARD_PERCENTILES
-                    .iter()
-                    .map(|&p| ReportPercentile {
-                        percentile: p,
-                        duration: Duration::from_micros(hist.value_at_percentile(p)),
-                    })
-                    .collect(),
-                total_requests: hist.len() as usize,
+                ..ReportSection::latency(name, Some(url), &hist)
             });
         }
 
         let mut error_latency = vec![];
-        for (url, hist) in self.error_histograms {
+        for (((name, url), kind), (hist, message)) in self.error_histograms {
             error_latency.push(ReportSection {
-                target: Some(url.clone()),
-                status_code: None,
-                percentiles: STANDARD_PERCENTILES
-                    .iter()
-                    .map(|&p| ReportPercentile {
-                        percentile: p,
-                        duration: Duration::from_micros(hist.value_at_percentile(p)),
-                    })
-                    .collect(),
-                total_requests: hist.len() as usize,
+                error_kind: Some(kind),
+                error_sample: Some(message),
+                ..ReportSection::latency(name, Some(url), &hist)
+            });
+        }
+
+        let mut timeout_latency = vec![];
+        for ((name, url), (hist, message)) in self.timeout_histograms {
+            timeout_latency.push(ReportSection {
+                error_kind: Some(ClientErrorKind::Timeout),
+                error_sample: Some(message),
+                ..ReportSection::latency(name, Some(url), &hist)
             });
         }
 
         let mut total_requests = 0;
         let mut request_delay = vec![];
-        for (url, hist) in self.delay_histograms {
-            request_delay.push(ReportSection {
-                target: Some(url.clone()),
-                status_code: None,
+        for ((name, url), hist) in self.delay_histograms {
+            request_delay.push(ReportSection::latency(name, Some(url), &hist));
+
+            total_requests += hist.len() as usize;
+        }
+
+        let mut address_latency = vec![];
+        for (((name, url), address), hist) in self.address_histograms {
+            address_latency.push(ReportSection {
+                address: Some(address),
+                ..ReportSection::latency(name, Some(url), &hist)
+            });
+        }
+
+        let mut phase_latency = vec![];
+        for (((name, url), phase), hist) in self.phase_histograms {
+            phase_latency.push(ReportSection {
+                phase: Some(phase),
+                ..ReportSection::latency(name, Some(url), &hist)
+            });
+        }
+
+        let mut response_size = vec![];
+        for ((key, status), hist) in self.size_histograms {
+            let total_bytes = self.size_totals[&(key.clone(), status)];
+            let (name, url) = key;
+            response_size.push(ReportSize {
+                request: name,
+                target: url,
+                status_code: status,
                 percentiles: STANDARD_PERCENTILES
                     .iter()
-                    .map(|&p| ReportPercentile {
+                    .map(|&p| ReportSizePercentile {
                         percentile: p,
-                        duration: Duration::from_micros(hist.value_at_percentile(p)),
+                        bytes: hist.value_at_percentile(p),
                     })
                     .collect(),
                 total_requests: hist.len() as usize,
+                total_bytes,
+                bytes_per_sec: total_bytes as f64 / total_duration.as_secs_f64(),
             });
+        }
 
-            total_requests += hist.len() as usize;
+        let mut total_dropped = 0;
+        let mut dropped_requests = vec![];
+        for ((name, target), count) in self.dropped_counts {
+            dropped_requests.push(ReportSection::count(name, target, count));
+
+            total_dropped += count;
         }
 
+        let mut total_proxied = 0;
+        let mut proxied_requests = vec![];
+        for ((name, url), count) in self.proxied_counts {
+            proxied_requests.push(ReportSection::count(name, Some(url), count));
+
+            total_proxied += count;
+        }
+
+        let mut scenario_latency = vec![];
+        for (name, hist) in self.scenario_histograms {
+            scenario_latency.push(ReportSection {
+                scenario: Some(name),
+                ..ReportSection::latency(None, None, &hist)
+            });
+        }
+
+        let mut aborted_scenarios = vec![];
+        for ((name, step), count) in self.aborted_counts {
+            aborted_scenarios.push(ReportSection {
+                scenario: Some(name),
+                ..ReportSection::count(Some(step), None, count)
+            });
+        }
+
+        let mut failed_assertions = self
+            .assertion_counts
+            .into_iter()
+            .map(|((request, assertion), total_failures)| ReportAssertion {
+                request,
+                assertion,
+                total_failures,
+            })
+            .collect::<Vec<_>>();
+        failed_assertions
+            .sort_by(|a, b| (&a.request, &a.assertion).cmp(&(&b.request, &b.assertion)));
+
+        let mut connections = self
+            .connection_counts
+            .into_iter()
+            .map(|(connection, total_requests)| ReportConnection {
+                connection,
+                total_requests,
+            })
+            .collect::<Vec<_>>();
+        connections.sort_by_key(|c| c.connection);
+
+        let mut protocols = self
+            .protocol_counts
+            .into_iter()
+            .map(|(protocol, total_requests)| ReportProtocol {
+                protocol,
+                total_requests,
+            })
+            .collect::<Vec<_>>();
+        protocols.sort_by(|a, b| a.protocol.cmp(&b.protocol));
+
+        let throughput = total_requests as f64 / total_duration.as_secs_f64();
+        let request_bytes_per_sec = self.request_bytes as f64 / total_duration.as_secs_f64();
+        let response_bytes_per_sec = self.response_bytes as f64 / total_duration.as_secs_f64();
+
         Report {
             response_latency,
             error_latency,
+            timeout_latency,
             request_delay,
+            phase_latency,
+            address_latency,
+            response_size,
+            dropped_requests,
+            proxied_requests,
+            scenario_latency,
+            aborted_scenarios,
+            failed_assertions,
+            connections,
+            protocols,
             total_requests,
-            total_duration: self.start.elapsed(),
+            total_dropped,
+            total_proxied,
+            total_duration,
+            throughput,
+            total_request_bytes: self.request_bytes,
+            total_response_bytes: self.response_bytes,
+            request_bytes_per_sec,
+            response_bytes_per_sec,
+            interrupted: self.interrupted,
         }
     }
 
     fn new_histogram() -> Histogram {
-        Histogram::new_with_bounds(1, 30 * 1_000_000, 3).unwrap()
+        // Allow the histogram to resize itself so that values beyond the initial 30s bound
+        // can still be recorded.
+        let mut hist = Histogram::new_with_bounds(1, 30 * 1_000_000, 3).unwrap();
+        hist.auto(true);
+        hist
     }
 }
@@ -0,0 +1,446 @@
+use std::collections::HashSet;
+
+use anyhow::{Context, Result};
+use hyper::{
+    header::{self, HeaderName, HeaderValue},
+    Body, HeaderMap, Method, Uri,
+};
+use metron::{Header, HttpMethod};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    assertion::Assertion,
+    connector::{self, UNIX_SCHEME},
+    scenario::Step,
+    target::default_weight,
+    template::{self, Variables},
+    Config, Target, Template,
+};
+
+/// Host header sent with requests to Unix domain sockets (unless one is configured).
+const UNIX_HOST: &str = "localhost";
+
+/// Template for a request that forms part of a weighted request mix.
+///
+/// Unlike a plain [Target][super::Target], which uses the profile-wide HTTP method, headers
+/// and payload, each request template defines its own. Requests are sampled from the
+/// templates in proportion to their weights.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RequestTemplate {
+    /// Name used to identify the template in the report.
+    pub name: String,
+    /// HTTP method.
+    #[serde(default)]
+    pub method: HttpMethod,
+    /// Request URL.
+    pub url: Template,
+    /// Request headers. These are applied on top of (and take precedence over) the
+    /// profile-wide headers. Header values may contain placeholders.
+    #[serde(default)]
+    pub headers: Vec<Header>,
+    /// Request body.
+    #[serde(default)]
+    pub body: Option<Template>,
+    /// Checks that each response must pass, in addition to the profile-wide assertions.
+    #[serde(default)]
+    pub assertions: Vec<Assertion>,
+    /// Weight of the template relative to the other templates and targets.
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+/// Request prepared from either a plain target or a request template, ready to be built
+/// each time it is sent.
+pub struct Request {
+    /// Name of the request template (if any).
+    pub name: Option<String>,
+    /// Request URL.
+    pub url: Template,
+    /// Parsed request URL, which is only available if the URL contains no placeholders.
+    uri: Option<Uri>,
+    method: Method,
+    /// Headers none of whose values contain placeholders.
+    headers: HeaderMap,
+    /// Headers any of whose values contain placeholders.
+    header_templates: Vec<(HeaderName, Template)>,
+    body: Option<Template>,
+    /// Checks that each response must pass.
+    assertions: Vec<Assertion>,
+    /// Weight of the request relative to the other requests.
+    pub weight: u32,
+    /// TLS server name that is sent as the Host header of HTTPS requests (if overridden).
+    server_name: Option<String>,
+}
+
+impl Request {
+    /// Prepares the request for a plain target, which uses the profile-wide HTTP method,
+    /// headers, payload and assertions.
+    pub fn from_target(target: &Target, config: &Config) -> Result<Self> {
+        Self::new(
+            None,
+            target.url.clone(),
+            config.http_method.into(),
+            parse_headers(&config.headers)?,
+            config.payload.clone(),
+            config.assertions.clone(),
+            target.weight,
+        )?
+        .with_server_name(config)
+    }
+
+    /// Prepares the request for a request template. The template's headers take precedence
+    /// over the profile-wide headers, whereas its assertions are checked alongside the
+    /// profile-wide assertions.
+    pub fn from_template(template: &RequestTemplate, config: &Config) -> Result<Self> {
+        Self::new(
+            Some(template.name.clone()),
+            template.url.clone(),
+            template.method.into(),
+            merge_headers(&config.headers, &template.headers)?,
+            template.body.clone(),
+            merge_assertions(&config.assertions, &template.assertions),
+            template.weight,
+        )?
+        .with_server_name(config)
+    }
+
+    /// Prepares the request for a scenario step. The step's headers take precedence over the
+    /// profile-wide headers, whereas its assertions are checked alongside the profile-wide
+    /// assertions.
+    pub fn from_step(step: &Step, config: &Config) -> Result<Self> {
+        Self::new(
+            Some(step.name.clone()),
+            step.url.clone(),
+            step.method.into(),
+            merge_headers(&config.headers, &step.headers)?,
+            step.body.clone(),
+            merge_assertions(&config.assertions, &step.assertions),
+            default_weight(),
+        )?
+        .with_server_name(config)
+    }
+
+    fn new(
+        name: Option<String>,
+        url: Template,
+        method: Method,
+        all_headers: HeaderMap<Template>,
+        body: Option<Template>,
+        assertions: Vec<Assertion>,
+        weight: u32,
+    ) -> Result<Self> {
+        // Parse whatever we can up front so that only placeholders need to be evaluated
+        // when the request is built.
+        let uri = if url.is_static() {
+            Some(parse_url(url.as_str()).context("Invalid target URL")?)
+        } else {
+            None
+        };
+
+        let mut headers = HeaderMap::new();
+        let mut header_templates = vec![];
+        for (name, value) in &all_headers {
+            // Every value of a header with placeholders is rendered with the request so that
+            // repeated headers keep their configured order.
+            let values = all_headers.get_all(name);
+            if values.iter().all(|v| v.is_static()) {
+                let value = value.as_str().parse().context("Invalid request header")?;
+                headers.append(name.clone(), value);
+            } else {
+                header_templates.push((name.clone(), value.clone()));
+            }
+        }
+
+        Ok(Self {
+            name,
+            url,
+            uri,
+            method,
+            headers,
+            header_templates,
+            body,
+            assertions,
+            weight,
+            server_name: None,
+        })
+    }
+
+    /// Addresses HTTPS requests to the overridden TLS server name (if any) unless a Host
+    /// header is configured.
+    fn with_server_name(mut self, config: &Config) -> Result<Self> {
+        let configured = self.headers.contains_key(header::HOST)
+            || self.header_templates.iter().any(|(n, _)| n == header::HOST);
+        if !configured && let Some(name) = &config.tls.server_name {
+            HeaderValue::try_from(name).context("Invalid TLS server name")?;
+            self.server_name = Some(name.clone());
+        }
+
+        Ok(self)
+    }
+
+    /// Returns the names of the variables that the request references.
+    pub fn variables(&self) -> impl Iterator<Item = &str> {
+        let headers = self
+            .header_templates
+            .iter()
+            .flat_map(|(_, v)| v.variables());
+        let body = self.body.iter().flat_map(|b| b.variables());
+        self.url.variables().chain(headers).chain(body)
+    }
+
+    /// Returns whether any checks are applied to the responses to the request.
+    pub fn has_assertions(&self) -> bool {
+        !self.assertions.is_empty()
+    }
+
+    /// Returns the names of the assertions that the response fails.
+    pub fn failed_assertions(&self, status: u16, body: &[u8]) -> Vec<String> {
+        self.assertions
+            .iter()
+            .filter(|a| !a.passes(status, body))
+            .map(|a| a.name.clone())
+            .collect()
+    }
+
+    /// Builds the request, evaluating any placeholders that it contains.
+    ///
+    /// # Arguments
+    ///
+    /// * `seq` - Sequence number of the request
+    /// * `vars` - Variables available to the request (e.g. the fields of a feeder record)
+    pub fn build(&self, seq: u64, vars: Option<&Variables>) -> Result<hyper::Request<Body>> {
+        let mut rng = rand::thread_rng();
+        let mut ctx = template::Context {
+            seq,
+            rng: &mut rng,
+            vars,
+        };
+
+        let uri = match &self.uri {
+            Some(uri) => uri.clone(),
+            None => {
+                let url = self.url.render(&mut ctx)?;
+                parse_url(&url).with_context(|| format!("Invalid request URL: {}", url))?
+            }
+        };
+
+        let body = match &self.body {
+            Some(body) => body.render(&mut ctx)?,
+            None => String::new(),
+        };
+
+        let is_unix = uri.scheme_str() == Some(UNIX_SCHEME);
+        let host = match (&self.server_name, uri.scheme_str()) {
+            (Some(name), Some("https")) => match uri.port_u16() {
+                Some(port) => Some(format!("{}:{}", name, port)),
+                None => Some(name.clone()),
+            },
+            _ => None,
+        };
+
+        let mut req = hyper::Request::builder()
+            .method(self.method.clone())
+            .uri(uri)
+            .body(Body::from(body))?;
+
+        let headers = req.headers_mut();
+        *headers = self.headers.clone();
+        for (name, value) in &self.header_templates {
+            let value = value.render(&mut ctx)?;
+            let value = HeaderValue::try_from(&value)
+                .with_context(|| format!("Invalid request header value: {}", value))?;
+            headers.append(name.clone(), value);
+        }
+
+        // The host of a Unix socket request's URI encodes the socket path, which makes for a
+        // meaningless Host header.
+        if is_unix && !headers.contains_key(header::HOST) {
+            headers.insert(header::HOST, HeaderValue::from_static(UNIX_HOST));
+        }
+
+        if let Some(host) = host {
+            headers.insert(header::HOST, HeaderValue::try_from(host)?);
+        }
+
+        Ok(req)
+    }
+}
+
+/// Parses a request URL.
+///
+/// Unix socket URLs take the form `unix://SOCKET_PATH:REQUEST_PATH` (e.g.
+/// `unix:///var/run/app.sock:/health`), where the request path defaults to `/`.
+fn parse_url(url: &str) -> Result<Uri> {
+    let unix = url
+        .strip_prefix(UNIX_SCHEME)
+        .and_then(|u| u.strip_prefix("://"));
+    match unix {
+        Some(rest) => {
+            let (socket, path) = rest.split_once(':').unwrap_or((rest, "/"));
+            connector::unix_uri(socket, path)
+        }
+        None => Ok(url.parse()?),
+    }
+}
+
+/// Parses the profile-wide headers and applies the overrides on top of them.
+fn merge_headers(headers: &[Header], overrides: &[Header]) -> Result<HeaderMap<Template>> {
+    let mut headers = parse_headers(headers)?;
+
+    // The first override of each header replaces its profile-wide values, whereas any later
+    // overrides of the same header are added alongside it.
+    let mut overridden = HashSet::new();
+    for header in overrides {
+        let (name, value) = parse_header(header)?;
+        if overridden.insert(name.clone()) {
+            headers.insert(name, value);
+        } else {
+            headers.append(name, value);
+        }
+    }
+
+    Ok(headers)
+}
+
+/// Returns the profile-wide assertions followed by those of a request template or step.
+fn merge_assertions(assertions: &[Assertion], overrides: &[Assertion]) -> Vec<Assertion> {
+    assertions.iter().chain(overrides).cloned().collect()
+}
+
+/// Parses the configured headers, whose values may contain placeholders.
+fn parse_headers(headers: &[Header]) -> Result<HeaderMap<Template>> {
+    headers.iter().map(parse_header).collect()
+}
+
+/// Parses a configured header, whose value may contain placeholders.
+fn parse_header(header: &Header) -> Result<(HeaderName, Template)> {
+    let parse = || -> Result<_> {
+        let name = header.name.parse::<HeaderName>()?;
+        let value = header.value.parse::<Template>()?;
+        Ok((name, value))
+    };
+
+    parse().context("Invalid request header")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn server_name_host_header() {
+        let mut config = Config::default();
+        config.tls.server_name = Some("api.example.com".to_owned());
+        let host = |url: &str, config: &Config| {
+            let target = Target::new(url.parse().unwrap());
+            let req = Request::from_target(&target, config).unwrap();
+            let req = req.build(0, None).unwrap();
+            req.headers().get(header::HOST).cloned()
+        };
+
+        // HTTPS requests are addressed to the server name (keeping the port of the URL).
+        let url = "https://10.0.0.1:8443/items";
+        assert_eq!(host(url, &config).unwrap(), "api.example.com:8443");
+        assert_eq!(host("http://10.0.0.1/", &config), None);
+
+        // A configured Host header takes precedence.
+        config.headers = vec![Header {
+            name: "Host".to_owned(),
+            value: "other.example.com".to_owned(),
+        }];
+        let url = "https://10.0.0.1/";
+        assert_eq!(host(url, &config).unwrap(), "other.example.com");
+    }
+
+    /// Returns a header with the name and value.
+    fn header(name: &str, value: &str) -> Header {
+        Header {
+            name: name.to_owned(),
+            value: value.to_owned(),
+        }
+    }
+
+    #[test]
+    fn repeated_template_headers() {
+        let config = Config {
+            headers: vec![header("Accept", "text/plain"), header("X-Test", "metron")],
+            ..Config::default()
+        };
+
+        let template = RequestTemplate {
+            name: "list-items".to_owned(),
+            method: HttpMethod::Get,
+            url: "https://example.com/items".parse().unwrap(),
+            headers: vec![
+                header("Accept", "application/json"),
+                header("Cookie", "a=1"),
+                header("Accept", "text/html"),
+                header("Cookie", "b=2"),
+            ],
+            body: None,
+            assertions: vec![],
+            weight: 1,
+        };
+
+        // Repeated headers are all sent and replace the profile-wide values.
+        let req = Request::from_template(&template, &config).unwrap();
+        let req = req.build(0, None).unwrap();
+        let values = |name| req.headers().get_all(name).iter().collect::<Vec<_>>();
+        assert_eq!(values("accept"), ["application/json", "text/html"]);
+        assert_eq!(values("cookie"), ["a=1", "b=2"]);
+        assert_eq!(values("x-test"), ["metron"]);
+    }
+
+    #[test]
+    fn profile_wide_assertions() {
+        let config = Config {
+            assertions: vec![serde_yaml::from_str("{name: ok, status: [200]}").unwrap()],
+            ..Config::default()
+        };
+        let names = |req: &Request| {
+            let names = req.assertions.iter().map(|a| a.name.clone());
+            names.collect::<Vec<_>>()
+        };
+
+        // Plain targets only have the profile-wide assertions, whereas steps add their own.
+        let target = Target::new("https://example.com/".parse().unwrap());
+        let req = Request::from_target(&target, &config).unwrap();
+        assert_eq!(names(&req), ["ok"]);
+
+        let step: Step = serde_yaml::from_str(
+            "
+name: cart
+url: https://example.com/cart
+assertions:
+  - name: has-items
+    body_contains: items
+",
+        )
+        .unwrap();
+        let req = Request::from_step(&step, &config).unwrap();
+        assert_eq!(names(&req), ["ok", "has-items"]);
+        assert_eq!(req.failed_assertions(500, b"items"), ["ok"]);
+    }
+
+    #[test]
+    fn repeated_step_headers() {
+        let step: Step = serde_yaml::from_str(
+            "
+name: cart
+url: https://example.com/cart
+headers:
+  - name: Cookie
+    value: session={{token}}
+  - name: Cookie
+    value: theme=dark
+",
+        )
+        .unwrap();
+
+        let vars = Variables::from([("token".to_owned(), "abc".to_owned())]);
+        let req = Request::from_step(&step, &Config::default()).unwrap();
+        let req = req.build(0, Some(&vars)).unwrap();
+        let cookies = req.headers().get_all("cookie").iter().collect::<Vec<_>>();
+        assert_eq!(cookies, ["session=abc", "theme=dark"]);
+    }
+}
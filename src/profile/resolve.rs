@@ -0,0 +1,127 @@
+use std::{fmt, net::IpAddr};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Addresses that a host name and port resolve to in place of a DNS lookup, in the same
+/// form as curl's `--resolve` option: `HOST:PORT:ADDR[,ADDR...]`.
+///
+/// IPv6 addresses may optionally be enclosed in square brackets.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Override {
+    /// Host name that is overridden.
+    pub host: String,
+    /// Port that the override applies to.
+    pub port: u16,
+    /// Addresses that the host name resolves to.
+    pub addrs: Vec<IpAddr>,
+}
+
+impl Override {
+    /// Returns the addresses that the host name and port resolve to if any of the overrides
+    /// apply to them.
+    pub fn find<'a>(overrides: &'a [Override], host: &str, port: u16) -> Option<&'a [IpAddr]> {
+        overrides
+            .iter()
+            .find(|o| o.port == port && o.host.eq_ignore_ascii_case(host))
+            .map(|o| o.addrs.as_slice())
+    }
+}
+
+impl TryFrom<String> for Override {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl std::str::FromStr for Override {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(3, ':');
+        let (Some(host), Some(port), Some(addrs)) = (parts.next(), parts.next(), parts.next())
+        else {
+            bail!("Resolve overrides must be specified in 'HOST:PORT:ADDR' format");
+        };
+
+        if host.is_empty() {
+            bail!("Resolve override is missing a host name: {}", s);
+        }
+
+        let port = port
+            .parse()
+            .with_context(|| format!("Invalid port in resolve override: {}", s))?;
+
+        let addrs = addrs
+            .split(',')
+            .map(|addr| {
+                let addr = addr.trim_start_matches('[').trim_end_matches(']');
+                addr.parse()
+                    .with_context(|| format!("Invalid address in resolve override: {}", s))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            host: host.to_owned(),
+            port,
+            addrs,
+        })
+    }
+}
+
+impl From<Override> for String {
+    fn from(value: Override) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for Override {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:", self.host, self.port)?;
+        for (i, addr) in self.addrs.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+
+            match addr {
+                IpAddr::V4(addr) => write!(f, "{}", addr)?,
+                IpAddr::V6(addr) => write!(f, "[{}]", addr)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_overrides() {
+        let o = "api.example.com:443:10.0.0.5".parse::<Override>().unwrap();
+        assert_eq!(o.host, "api.example.com");
+        assert_eq!(o.port, 443);
+        assert_eq!(o.addrs, ["10.0.0.5".parse::<IpAddr>().unwrap()]);
+
+        let o = "example.com:80:10.0.0.5,[::1]".parse::<Override>().unwrap();
+        assert_eq!(o.addrs[1], "::1".parse::<IpAddr>().unwrap());
+        assert_eq!(o.to_string(), "example.com:80:10.0.0.5,[::1]");
+
+        let overrides = [o];
+        assert!(Override::find(&overrides, "EXAMPLE.com", 80).is_some());
+        assert!(Override::find(&overrides, "example.com", 443).is_none());
+
+        for o in [
+            "example.com:443",
+            ":443:10.0.0.5",
+            "a:port:10.0.0.5",
+            "a:443:x",
+        ] {
+            assert!(o.parse::<Override>().is_err(), "{}", o);
+        }
+    }
+}
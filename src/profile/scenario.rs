@@ -0,0 +1,253 @@
+use anyhow::{bail, Context, Result};
+use hyper::HeaderMap;
+use metron::{Header, HttpMethod};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::{Assertion, Template};
+
+/// Default scenario name.
+const DEFAULT_NAME: &str = "scenario";
+
+/// Ordered sequence of requests that together form a flow (e.g. log in, view the cart and
+/// check out).
+///
+/// Each plan tick starts one iteration of the scenario. Values extracted from the response
+/// to one step are bound to variables that later steps can reference using placeholders.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Scenario {
+    /// Name used to identify the scenario in the report.
+    #[serde(default = "default_name")]
+    pub name: String,
+    /// Steps in the order in which they are performed.
+    pub steps: Vec<Step>,
+}
+
+fn default_name() -> String {
+    DEFAULT_NAME.to_owned()
+}
+
+/// Single request within a [Scenario].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Step {
+    /// Name used to identify the step in the report.
+    pub name: String,
+    /// HTTP method.
+    #[serde(default)]
+    pub method: HttpMethod,
+    /// Request URL.
+    pub url: Template,
+    /// Request headers. These are applied on top of (and take precedence over) the
+    /// profile-wide headers. Header values may contain placeholders.
+    #[serde(default)]
+    pub headers: Vec<Header>,
+    /// Request body.
+    #[serde(default)]
+    pub body: Option<Template>,
+    /// Values to extract from the response and bind to variables for later steps.
+    #[serde(default)]
+    pub extract: Vec<Extractor>,
+    /// Checks that the response must pass (in addition to the profile-wide assertions). The
+    /// iteration is aborted if any check fails.
+    #[serde(default)]
+    pub assertions: Vec<Assertion>,
+}
+
+/// Extracts a value from a response and binds it to a variable.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Extractor {
+    /// Name of the variable that the extracted value is bound to.
+    pub var: String,
+    /// Where the value is extracted from.
+    #[serde(flatten)]
+    pub source: Source,
+}
+
+/// Part of a response that a value is extracted from.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    /// JSON path into the response body (e.g. `$.data.items[0].id`).
+    Json(JsonPath),
+    /// Name of a response header.
+    Header(String),
+    /// Regular expression that is matched against the response body. The value of the first
+    /// capture group is extracted if the expression has one, otherwise the whole match is.
+    Regex(#[serde(with = "serde_regex")] Regex),
+}
+
+impl Extractor {
+    /// Extracts the value from the response.
+    pub fn extract(&self, headers: &HeaderMap, body: &[u8]) -> Result<String> {
+        match &self.source {
+            Source::Json(path) => {
+                let value = serde_json::from_slice(body).context("Response is not valid JSON")?;
+                path.select(&value)
+            }
+            Source::Header(name) => {
+                let value = headers
+                    .get(name.as_str())
+                    .with_context(|| format!("Response has no {} header", name))?;
+                Ok(value.to_str()?.to_owned())
+            }
+            Source::Regex(re) => {
+                let body = std::str::from_utf8(body).context("Response is not valid UTF-8")?;
+                let captures = re
+                    .captures(body)
+                    .with_context(|| format!("Response does not match {}", re))?;
+                let value = captures.get(1).or_else(|| captures.get(0)).unwrap();
+                Ok(value.as_str().to_owned())
+            }
+        }
+    }
+}
+
+/// Path into a JSON document.
+///
+/// Paths start at the root (`$`) and consist of object keys separated by dots and array
+/// indices in square brackets, e.g. `$.items[0].id`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct JsonPath {
+    /// Unparsed path source.
+    source: String,
+    /// Parsed path segments.
+    segments: Vec<Segment>,
+}
+
+#[derive(Clone, Debug)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+impl JsonPath {
+    /// Returns the value at the path.
+    pub fn find<'a>(&self, value: &'a serde_json::Value) -> Result<&'a serde_json::Value> {
+        let mut value = value;
+        for segment in &self.segments {
+            let next = match segment {
+                Segment::Key(key) => value.get(key),
+                Segment::Index(index) => value.get(index),
+            };
+
+            value = next.with_context(|| format!("Response has no value at {}", self.source))?;
+        }
+
+        Ok(value)
+    }
+
+    /// Selects the value at the path. Strings are returned as is whereas other values are
+    /// returned as JSON.
+    pub fn select(&self, value: &serde_json::Value) -> Result<String> {
+        Ok(match self.find(value)? {
+            serde_json::Value::String(s) => s.clone(),
+            v => v.to_string(),
+        })
+    }
+}
+
+impl TryFrom<String> for JsonPath {
+    type Error = anyhow::Error;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        let Some(mut rest) = source.strip_prefix('$') else {
+            bail!("JSON path must start with $: {}", source);
+        };
+
+        let mut segments = vec![];
+        while !rest.is_empty() {
+            if let Some(path) = rest.strip_prefix('.') {
+                let end = path.find(['.', '[']).unwrap_or(path.len());
+                if end == 0 {
+                    bail!("Empty key in JSON path: {}", source);
+                }
+
+                segments.push(Segment::Key(path[..end].to_owned()));
+                rest = &path[end..];
+            } else if let Some(path) = rest.strip_prefix('[') {
+                let end = path
+                    .find(']')
+                    .with_context(|| format!("Unterminated index in JSON path: {}", source))?;
+                let index = path[..end]
+                    .parse()
+                    .with_context(|| format!("Invalid index in JSON path: {}", source))?;
+
+                segments.push(Segment::Index(index));
+                rest = &path[end + 1..];
+            } else {
+                bail!("Invalid JSON path: {}", source);
+            }
+        }
+
+        Ok(Self { source, segments })
+    }
+}
+
+impl From<JsonPath> for String {
+    fn from(path: JsonPath) -> Self {
+        path.source
+    }
+}
+
+/// (De)serializes regular expressions from/to their source.
+pub(super) mod serde_regex {
+    use regex::Regex;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(re: &Regex, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(re.as_str())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        Regex::new(&source).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extractor(yaml: &str) -> Extractor {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn extract_json() {
+        let body = br#"{"token": "abc", "items": [{"id": 7}]}"#;
+        let headers = HeaderMap::new();
+
+        let token = extractor("{var: token, json: $.token}");
+        assert_eq!(token.extract(&headers, body).unwrap(), "abc");
+
+        let id = extractor("{var: id, json: '$.items[0].id'}");
+        assert_eq!(id.extract(&headers, body).unwrap(), "7");
+
+        let missing = extractor("{var: id, json: '$.items[1].id'}");
+        assert!(missing.extract(&headers, body).is_err());
+    }
+
+    #[test]
+    fn extract_header_and_regex() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-session", "s1".parse().unwrap());
+        let body = b"order=42; status=ok";
+
+        let session = extractor("{var: session, header: X-Session}");
+        assert_eq!(session.extract(&headers, body).unwrap(), "s1");
+
+        let order = extractor(r"{var: order, regex: 'order=(\d+)'}");
+        assert_eq!(order.extract(&headers, body).unwrap(), "42");
+
+        let status = extractor(r"{var: status, regex: 'status=\w+'}");
+        assert_eq!(status.extract(&headers, body).unwrap(), "status=ok");
+    }
+
+    #[test]
+    fn invalid_json_paths() {
+        for path in ["token", "$.", "$[x]", "$[0", "$..a"] {
+            assert!(JsonPath::try_from(path.to_owned()).is_err(), "{}", path);
+        }
+    }
+}
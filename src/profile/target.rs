@@ -0,0 +1,277 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use rand::{distributions::WeightedIndex, prelude::Distribution, rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use super::Template;
+
+/// Performance profile target.
+///
+/// Targets may be deserialized from either a plain URL or an object containing the URL and
+/// the target's weight.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(from = "TargetDef")]
+pub struct Target {
+    /// Target URL (which may contain placeholders).
+    pub url: Template,
+    /// Weight of the target relative to the other targets.
+    pub weight: u32,
+}
+
+impl Target {
+    pub fn new(url: Template) -> Self {
+        Self {
+            url,
+            weight: DEFAULT_WEIGHT,
+        }
+    }
+}
+
+/// Default target weight.
+const DEFAULT_WEIGHT: u32 = 1;
+
+/// Serialized forms of a [Target].
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TargetDef {
+    Url(Template),
+    Weighted {
+        url: Template,
+        #[serde(default = "default_weight")]
+        weight: u32,
+    },
+}
+
+pub(super) fn default_weight() -> u32 {
+    DEFAULT_WEIGHT
+}
+
+impl From<TargetDef> for Target {
+    fn from(def: TargetDef) -> Self {
+        match def {
+            TargetDef::Url(url) => Target::new(url),
+            TargetDef::Weighted { url, weight } => Target { url, weight },
+        }
+    }
+}
+
+/// Strategy used to select the target of each request.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Strategy {
+    /// Cycle through the targets in proportion to their weights.
+    #[default]
+    RoundRobin,
+
+    /// Pick targets at random in proportion to their weights.
+    Random,
+
+    /// Pick the target with the fewest outstanding requests relative to its weight.
+    LeastOutstanding,
+}
+
+/// Selects the target of each request according to a [Strategy].
+pub struct Selector {
+    /// Target weights.
+    weights: Vec<u32>,
+    /// Mutable state used by the selection strategy.
+    state: Mutex<State>,
+    /// Number of outstanding requests for each target.
+    outstanding: Arc<Vec<AtomicUsize>>,
+}
+
+enum State {
+    /// Smooth weighted round-robin state: the current weight of each target.
+    RoundRobin(Vec<i64>),
+    /// Weighted random state.
+    Random(Box<StdRng>, WeightedIndex<u32>),
+    /// Least outstanding requests state: the current weight of each target, which is used
+    /// to break ties using round-robin.
+    LeastOutstanding(Vec<i64>),
+}
+
+impl Selector {
+    /// Creates a new `Selector`.
+    ///
+    /// # Arguments
+    ///
+    /// * `weights` - Weights of the targets to select between
+    /// * `strategy` - Selection strategy
+    /// * `seed` - Seed for random selection (a random seed is used if not specified)
+    pub fn new(weights: &[u32], strategy: Strategy, seed: Option<u64>) -> Result<Self> {
+        let weights = weights.to_vec();
+        if weights.iter().all(|&w| w == 0) {
+            bail!("At least one target must have a non-zero weight");
+        }
+
+        let state = match strategy {
+            Strategy::RoundRobin => State::RoundRobin(vec![0; weights.len()]),
+            Strategy::Random => {
+                let rng = match seed {
+                    Some(seed) => StdRng::seed_from_u64(seed),
+                    None => StdRng::from_entropy(),
+                };
+
+                State::Random(Box::new(rng), WeightedIndex::new(&weights)?)
+            }
+            Strategy::LeastOutstanding => State::LeastOutstanding(vec![0; weights.len()]),
+        };
+
+        let outstanding = weights.iter().map(|_| AtomicUsize::new(0)).collect();
+
+        Ok(Self {
+            weights,
+            state: Mutex::new(state),
+            outstanding: Arc::new(outstanding),
+        })
+    }
+
+    /// Selects the target for the next request.
+    ///
+    /// The target is considered to have an outstanding request until the returned
+    /// [Selection] is dropped.
+    pub fn select(&self) -> Selection {
+        let mut state = self.state.lock().unwrap();
+        let index = match &mut *state {
+            State::RoundRobin(current) => self.round_robin(current, |_| true),
+            State::Random(rng, dist) => dist.sample(rng.as_mut()),
+            State::LeastOutstanding(current) => {
+                // Compare outstanding/weight ratios using cross-multiplication to avoid
+                // floating point arithmetic.
+                let load = |i: usize| {
+                    let outstanding = self.outstanding[i].load(Ordering::Relaxed) as u64;
+                    (outstanding, self.weights[i] as u64)
+                };
+
+                let (o, w) = (0..self.weights.len())
+                    .filter(|&i| self.weights[i] > 0)
+                    .map(load)
+                    .min_by(|&(oi, wi), &(oj, wj)| (oi * wj).cmp(&(oj * wi)))
+                    .unwrap();
+
+                // Break ties between the least loaded targets using round-robin so that
+                // idle targets still receive requests in proportion to their weights.
+                self.round_robin(current, |i| {
+                    let (oi, wi) = load(i);
+                    wi > 0 && oi * w == o * wi
+                })
+            }
+        };
+
+        self.outstanding[index].fetch_add(1, Ordering::Relaxed);
+
+        Selection {
+            index,
+            outstanding: self.outstanding.clone(),
+        }
+    }
+
+    /// Selects a target using smooth weighted round-robin.
+    ///
+    /// Each eligible target's current weight is increased by its weight and the target with
+    /// the highest current weight is selected and has its current weight reduced by the total
+    /// weight of the eligible targets.
+    fn round_robin(&self, current: &mut [i64], eligible: impl Fn(usize) -> bool) -> usize {
+        let mut total = 0;
+        let mut selected = None;
+        for (i, &w) in self.weights.iter().enumerate() {
+            if !eligible(i) {
+                continue;
+            }
+
+            current[i] += w as i64;
+            total += w as i64;
+            if selected.is_none_or(|s: usize| current[i] > current[s]) {
+                selected = Some(i);
+            }
+        }
+
+        // Safe to unwrap since at least one target is always eligible.
+        let selected = selected.unwrap();
+        current[selected] -= total;
+        selected
+    }
+}
+
+/// A selected target.
+pub struct Selection {
+    /// Index of the selected target.
+    index: usize,
+    /// Outstanding request counts that are decremented when the selection is dropped.
+    outstanding: Arc<Vec<AtomicUsize>>,
+}
+
+impl Selection {
+    /// Returns the index of the selected target.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl Drop for Selection {
+    fn drop(&mut self) {
+        self.outstanding[self.index].fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(selector: &Selector, n: usize, len: usize) -> Vec<usize> {
+        let mut counts = vec![0; len];
+        for _ in 0..n {
+            counts[selector.select().index()] += 1;
+        }
+
+        counts
+    }
+
+    #[test]
+    fn weighted_round_robin() {
+        let selector = Selector::new(&[9, 1], Strategy::RoundRobin, None).unwrap();
+        assert_eq!(counts(&selector, 1000, 2), vec![900, 100]);
+    }
+
+    #[test]
+    fn seeded_random_is_reproducible() {
+        let a = Selector::new(&[3, 1], Strategy::Random, Some(42)).unwrap();
+        let b = Selector::new(&[3, 1], Strategy::Random, Some(42)).unwrap();
+
+        let a = (0..100).map(|_| a.select().index()).collect::<Vec<_>>();
+        let b = (0..100).map(|_| b.select().index()).collect::<Vec<_>>();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn least_outstanding() {
+        let selector = Selector::new(&[1, 1], Strategy::LeastOutstanding, None).unwrap();
+
+        // While the first selection is outstanding the other target should be preferred.
+        let first = selector.select();
+        let second = selector.select();
+        assert_ne!(first.index(), second.index());
+
+        // Once the first selection completes its target should be selected again.
+        let idx = first.index();
+        drop(first);
+        assert_eq!(selector.select().index(), idx);
+    }
+
+    #[test]
+    fn deserialize_plain_and_weighted_targets() {
+        let yaml = "
+- https://example.com/a
+- url: https://example.com/b
+  weight: 9
+";
+        let targets: Vec<Target> = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(targets[0].weight, 1);
+        assert_eq!(targets[1].weight, 9);
+    }
+}
@@ -0,0 +1,287 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context as _, Result};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Opening delimiter of a placeholder.
+const OPEN: &str = "{{";
+
+/// Closing delimiter of a placeholder.
+const CLOSE: &str = "}}";
+
+/// String containing placeholders that are evaluated each time a request is sent.
+///
+/// Templates are parsed once (when the configuration is loaded) so that rendering them for
+/// each request only requires the placeholders to be evaluated. The following placeholders
+/// are supported:
+///
+/// * `{{seq}}` - Sequence number of the request (starting at 0)
+/// * `{{random:MIN:MAX}}` - Random integer between `MIN` and `MAX` (inclusive)
+/// * `{{uuid}}` - Random (v4) UUID
+/// * `{{timestamp}}` - Current Unix time in milliseconds (`{{timestamp:s}}`,
+///   `{{timestamp:ms}}` and `{{timestamp:us}}` select the unit)
+/// * `{{pick:A|B|C}}` - Random pick from the list of `|`-separated values
+/// * `{{NAME}}` - Value of the variable `NAME` (e.g. a field of a feeder record)
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Template {
+    /// Unparsed template source.
+    source: String,
+    /// Parsed template parts.
+    parts: Vec<Part>,
+}
+
+#[derive(Clone, Debug)]
+enum Part {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+#[derive(Clone, Debug)]
+enum Placeholder {
+    Seq,
+    Random { min: i64, max: i64 },
+    Uuid,
+    Timestamp(TimestampUnit),
+    Pick(Vec<String>),
+    Variable(String),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum TimestampUnit {
+    Seconds,
+    Millis,
+    Micros,
+}
+
+/// Named values that may be referenced by templates.
+pub type Variables = HashMap<String, String>;
+
+/// Per-request values used to evaluate placeholders.
+pub struct Context<'a, R: Rng> {
+    /// Sequence number of the request.
+    pub seq: u64,
+    /// Source of randomness.
+    pub rng: &'a mut R,
+    /// Variables that are available to the request (if any).
+    pub vars: Option<&'a Variables>,
+}
+
+impl Template {
+    /// Returns the unparsed template source.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns whether the template contains any placeholders. Static templates always
+    /// render to their source.
+    pub fn is_static(&self) -> bool {
+        self.parts.iter().all(|p| matches!(p, Part::Literal(_)))
+    }
+
+    /// Returns the names of the variables that the template references.
+    pub fn variables(&self) -> impl Iterator<Item = &str> {
+        self.parts.iter().filter_map(|p| match p {
+            Part::Placeholder(Placeholder::Variable(name)) => Some(name.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Renders the template by evaluating its placeholders.
+    ///
+    /// Rendering fails if the template references a variable that is not available.
+    pub fn render<R: Rng>(&self, ctx: &mut Context<R>) -> Result<String> {
+        let mut out = String::with_capacity(self.source.len());
+        for part in &self.parts {
+            match part {
+                Part::Literal(s) => out.push_str(s),
+                Part::Placeholder(p) => p.render(ctx, &mut out)?,
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+impl Placeholder {
+    fn parse(expr: &str) -> Result<Self> {
+        let (name, args) = match expr.split_once(':') {
+            Some((name, args)) => (name.trim(), Some(args)),
+            None => (expr, None),
+        };
+
+        let placeholder = match (name, args) {
+            ("seq", None) => Self::Seq,
+            ("uuid", None) => Self::Uuid,
+            ("random", Some(args)) => {
+                let (min, max) = args
+                    .split_once(':')
+                    .context("Random placeholders must be specified as {{random:MIN:MAX}}")?;
+                let (min, max) = (min.trim().parse()?, max.trim().parse()?);
+                if min > max {
+                    bail!("Random placeholder minimum cannot exceed the maximum");
+                }
+
+                Self::Random { min, max }
+            }
+            ("timestamp", unit) => Self::Timestamp(match unit.map(str::trim) {
+                None | Some("ms") => TimestampUnit::Millis,
+                Some("s") => TimestampUnit::Seconds,
+                Some("us") => TimestampUnit::Micros,
+                Some(unit) => bail!("Unknown timestamp unit: {}", unit),
+            }),
+            ("pick", Some(args)) => Self::Pick(args.split('|').map(ToOwned::to_owned).collect()),
+            (name, None) if is_variable_name(name) => Self::Variable(name.to_owned()),
+            _ => bail!("Unknown placeholder: {{{{{}}}}}", expr),
+        };
+
+        Ok(placeholder)
+    }
+
+    fn render<R: Rng>(&self, ctx: &mut Context<R>, out: &mut String) -> Result<()> {
+        use std::fmt::Write;
+
+        // Writing to a string cannot fail.
+        match self {
+            Self::Seq => write!(out, "{}", ctx.seq).unwrap(),
+            Self::Random { min, max } => write!(out, "{}", ctx.rng.gen_range(*min..=*max)).unwrap(),
+            Self::Uuid => {
+                let mut bytes = [0; 16];
+                ctx.rng.fill(&mut bytes);
+                let uuid = uuid::Builder::from_random_bytes(bytes).into_uuid();
+                write!(out, "{}", uuid).unwrap()
+            }
+            Self::Timestamp(unit) => {
+                // The system clock is assumed to be after the Unix epoch.
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default();
+                let ts = match unit {
+                    TimestampUnit::Seconds => now.as_secs() as u128,
+                    TimestampUnit::Millis => now.as_millis(),
+                    TimestampUnit::Micros => now.as_micros(),
+                };
+
+                write!(out, "{}", ts).unwrap()
+            }
+            Self::Pick(values) => out.push_str(&values[ctx.rng.gen_range(0..values.len())]),
+            Self::Variable(name) => {
+                let value = ctx
+                    .vars
+                    .and_then(|vars| vars.get(name))
+                    .with_context(|| format!("Undefined variable: {}", name))?;
+                out.push_str(value)
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns whether the name is a valid variable name.
+fn is_variable_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+impl TryFrom<String> for Template {
+    type Error = anyhow::Error;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        let mut parts = vec![];
+        let mut rest = source.as_str();
+        while let Some(start) = rest.find(OPEN) {
+            if start > 0 {
+                parts.push(Part::Literal(rest[..start].to_owned()));
+            }
+
+            let expr = &rest[start + OPEN.len()..];
+            let end = expr
+                .find(CLOSE)
+                .with_context(|| format!("Unterminated placeholder in template: {}", source))?;
+
+            let placeholder = Placeholder::parse(expr[..end].trim())
+                .with_context(|| format!("Invalid placeholder in template: {}", source))?;
+
+            parts.push(Part::Placeholder(placeholder));
+            rest = &expr[end + CLOSE.len()..];
+        }
+
+        if !rest.is_empty() {
+            parts.push(Part::Literal(rest.to_owned()));
+        }
+
+        Ok(Self { source, parts })
+    }
+}
+
+impl std::str::FromStr for Template {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Template::try_from(s.to_owned())
+    }
+}
+
+impl From<Template> for String {
+    fn from(template: Template) -> Self {
+        template.source
+    }
+}
+
+impl fmt::Debug for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.source.fmt(f)
+    }
+}
+
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.source.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(template: &str, seq: u64) -> String {
+        let template = template.parse::<Template>().unwrap();
+        let vars = Variables::from([("user".to_owned(), "alice".to_owned())]);
+        let mut ctx = Context {
+            seq,
+            rng: &mut rand::thread_rng(),
+            vars: Some(&vars),
+        };
+
+        template.render(&mut ctx).unwrap()
+    }
+
+    #[test]
+    fn render_placeholders() {
+        assert_eq!(render("/items/{{seq}}?a=b", 42), "/items/42?a=b");
+        assert_eq!(render("{{ pick:x }}-{{random:7:7}}", 0), "x-7");
+        assert_eq!(render("{{uuid}}", 0).len(), 36);
+        assert!(render("{{timestamp:s}}", 0).parse::<u64>().unwrap() > 0);
+        assert_eq!(render("/users/{{user}}", 0), "/users/alice");
+    }
+
+    #[test]
+    fn invalid_templates() {
+        for template in [
+            "{{seq",
+            "{{unknown:x}}",
+            "{{random:9:1}}",
+            "{{timestamp:h}}",
+        ] {
+            assert!(template.parse::<Template>().is_err(), "{}", template);
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio_native_tls::native_tls::{Certificate, Identity, TlsConnector};
+
+/// PEM header that starts each certificate in a CA bundle.
+const PEM_BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+
+/// PEM footer that ends each certificate in a CA bundle.
+const PEM_END: &str = "-----END CERTIFICATE-----";
+
+/// TLS configuration applied to connections to HTTPS targets.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Config {
+    /// PEM files of CA certificates that are trusted in addition to the system's trusted
+    /// certificates. Each file may contain multiple certificates.
+    #[serde(default)]
+    pub ca_certs: Vec<PathBuf>,
+    /// PEM file of the client certificate that is presented for mutual TLS.
+    #[serde(default)]
+    pub client_cert: Option<PathBuf>,
+    /// PEM file of the (PKCS #8) private key of the client certificate.
+    #[serde(default)]
+    pub client_key: Option<PathBuf>,
+    /// Whether to skip verification of server certificates and host names. Only intended
+    /// for local testing.
+    #[serde(default)]
+    pub insecure: bool,
+    /// Server name that is sent (via SNI and as the Host header) and verified instead of the
+    /// target's host name. All HTTPS targets must share the same host.
+    #[serde(default)]
+    pub server_name: Option<String>,
+}
+
+impl Config {
+    /// Builds the TLS connector, loading any certificates and keys from their files.
+    ///
+    /// # Arguments
+    ///
+    /// * `alpn` - Protocols to offer during ALPN negotiation
+    pub fn connector(&self, alpn: &[&str]) -> Result<TlsConnector> {
+        let mut builder = TlsConnector::builder();
+        builder.request_alpns(alpn);
+
+        for path in &self.ca_certs {
+            let bundle = fs::read_to_string(path)
+                .with_context(|| format!("Could not read CA certificate file {:?}", path))?;
+
+            let certs = pem_certificates(&bundle)
+                .with_context(|| format!("Invalid CA certificate file {:?}", path))?;
+
+            for cert in certs {
+                let cert = Certificate::from_pem(cert.as_bytes())
+                    .with_context(|| format!("Invalid CA certificate in {:?}", path))?;
+                builder.add_root_certificate(cert);
+            }
+        }
+
+        match (&self.client_cert, &self.client_key) {
+            (Some(cert_path), Some(key_path)) => {
+                let cert = fs::read(cert_path).with_context(|| {
+                    format!("Could not read client certificate file {:?}", cert_path)
+                })?;
+                let key = fs::read(key_path)
+                    .with_context(|| format!("Could not read client key file {:?}", key_path))?;
+
+                let identity = Identity::from_pkcs8(&cert, &key)
+                    .context("Invalid client certificate or key")?;
+                builder.identity(identity);
+            }
+            (Some(_), None) => bail!("A client certificate requires a client key"),
+            (None, Some(_)) => bail!("A client key requires a client certificate"),
+            (None, None) => {}
+        }
+
+        if self.insecure {
+            builder
+                .danger_accept_invalid_certs(true)
+                .danger_accept_invalid_hostnames(true);
+        }
+
+        Ok(builder.build()?)
+    }
+}
+
+/// Splits a PEM bundle into its individual certificates.
+///
+/// Returns an error if the bundle contains no certificates or an unterminated certificate.
+fn pem_certificates(bundle: &str) -> Result<Vec<&str>> {
+    let mut certs = vec![];
+    let mut rest = bundle;
+    while let Some(start) = rest.find(PEM_BEGIN) {
+        let Some(len) = rest[start..].find(PEM_END) else {
+            bail!("Unterminated certificate");
+        };
+
+        let end = start + len + PEM_END.len();
+        certs.push(&rest[start..end]);
+        rest = &rest[end..];
+    }
+
+    if certs.is_empty() {
+        bail!("No certificates found");
+    }
+
+    Ok(certs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_pem_bundle() {
+        let bundle = format!("# root\n{0}\nAAA\n{1}\n{0}\nBBB\n{1}\n", PEM_BEGIN, PEM_END);
+        let certs = pem_certificates(&bundle).unwrap();
+        assert_eq!(certs.len(), 2);
+        assert_eq!(certs[1], [PEM_BEGIN, "BBB", PEM_END].join("\n"));
+
+        assert!(pem_certificates("").is_err());
+        assert!(pem_certificates(&[PEM_BEGIN, "AAA"].join("\n")).is_err());
+    }
+}
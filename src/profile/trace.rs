@@ -0,0 +1,470 @@
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// Rate time series (e.g. exported from production traffic) that is replayed by a trace
+/// segment of the plan.
+///
+/// The trace is replayed over its own span (i.e. from its first to its last sample) unless
+/// a duration is given, in which case the trace is compressed (or stretched) to fit it.
+/// The rates of the trace are kept as they are (other than being scaled) so compressing the
+/// trace replays its shape faster rather than sending the same number of requests.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Trace {
+    /// Path of the file containing the time series.
+    pub path: PathBuf,
+    /// Format of the file. If not specified, the format is inferred from the file extension.
+    #[serde(default)]
+    pub format: Option<Format>,
+    /// How the rate is interpolated between samples.
+    #[serde(default)]
+    pub interpolation: Interpolation,
+    /// Duration over which the trace is replayed.
+    #[serde(default)]
+    #[serde(with = "humantime_serde")]
+    pub duration: Option<Duration>,
+    /// Factor that the rates of the trace are multiplied by.
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    /// Samples of the time series, which are read from the file when the trace is loaded.
+    #[serde(skip)]
+    pub(crate) samples: Vec<Sample>,
+    /// Number of requests sent by the trace (in its own time) up to each sample.
+    #[serde(skip)]
+    requests: OnceLock<Vec<f64>>,
+}
+
+/// Request rate at a point in a time series.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Sample {
+    /// Number of seconds since the first sample of the time series.
+    pub offset: f64,
+    /// Request rate (in requests per second).
+    pub rate: f64,
+}
+
+/// Format of a trace file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// Comma-separated `timestamp,rps` values with an optional header row.
+    Csv,
+    /// JSON array of `{"timestamp": ..., "rps": ...}` objects or `[timestamp, rps]` pairs.
+    Json,
+}
+
+/// How the rate of a trace is interpolated between samples.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Interpolation {
+    /// Each sample's rate is held until the next sample.
+    Step,
+    /// The rate varies linearly from each sample to the next.
+    #[default]
+    Linear,
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+impl Trace {
+    /// Creates a trace that is replayed from the file at its own speed.
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            format: None,
+            interpolation: Interpolation::default(),
+            duration: None,
+            scale: default_scale(),
+            samples: vec![],
+            requests: OnceLock::new(),
+        }
+    }
+
+    /// Reads the samples of the time series from the file.
+    pub fn load(&mut self) -> Result<()> {
+        let format = match self.format {
+            Some(format) => format,
+            None => Self::infer_format(&self.path)?,
+        };
+
+        let file = File::open(&self.path)
+            .with_context(|| format!("Error opening trace file: {}", self.path.display()))?;
+
+        let points = match format {
+            Format::Csv => Self::read_csv(file),
+            Format::Json => Self::read_json(file),
+        }
+        .with_context(|| format!("Error reading trace file: {}", self.path.display()))?;
+
+        let first = points.first().map_or(0.0, |&(timestamp, _)| timestamp);
+        self.samples = points
+            .into_iter()
+            .map(|(timestamp, rate)| Sample {
+                offset: timestamp - first,
+                rate,
+            })
+            .collect();
+
+        self.requests = OnceLock::new();
+        Ok(())
+    }
+
+    /// Checks that the trace describes a schedule that can be generated.
+    pub fn validate(&self) -> Result<()> {
+        if self.samples.len() < 2 {
+            bail!(
+                "Trace must contain at least two samples: {}",
+                self.path.display()
+            );
+        }
+
+        if self
+            .samples
+            .iter()
+            .any(|s| !s.rate.is_finite() || s.rate < 0.0)
+        {
+            bail!("Trace rates must be non-negative: {}", self.path.display());
+        }
+
+        let increasing = self.samples.windows(2).all(|w| w[0].offset < w[1].offset);
+        if !increasing || self.samples.iter().any(|s| !s.offset.is_finite()) {
+            bail!(
+                "Trace timestamps must be strictly increasing: {}",
+                self.path.display()
+            );
+        }
+
+        if !self.scale.is_finite() || self.scale <= 0.0 {
+            bail!("Trace scale must be greater than zero");
+        }
+
+        if self.duration.is_some_and(|d| d.is_zero()) {
+            bail!("Trace duration cannot be zero");
+        }
+
+        Ok(())
+    }
+
+    /// Returns the duration over which the trace is replayed.
+    pub fn duration(&self) -> Duration {
+        self.duration
+            .unwrap_or_else(|| Duration::from_secs_f64(self.span()))
+    }
+
+    /// Calculates the number of requests that should have been sent `secs` into the replay.
+    pub fn requests_until(&self, secs: f64) -> f64 {
+        let speed = self.speed();
+        let offset = self.samples[0].offset + (secs * speed).clamp(0.0, self.span());
+        let requests = self.requests();
+
+        let i = self.interval(self.samples.partition_point(|s| s.offset <= offset));
+        let elapsed = offset - self.samples[i].offset;
+        let traced = requests[i] + self.traced_within(i, elapsed);
+
+        self.scale * traced / speed
+    }
+
+    /// Calculates how many seconds into the replay the given number of requests should have
+    /// been sent (i.e. the inverse of [`requests_until`][Self::requests_until]).
+    pub fn time_of(&self, requests: f64) -> f64 {
+        let speed = self.speed();
+        let traced = requests * speed / self.scale;
+
+        // Intervals without any requests are skipped as the last interval that starts at or
+        // before the number of requests is chosen.
+        let cumulative = self.requests();
+        let i = self.interval(cumulative.partition_point(|&r| r <= traced));
+        let remaining = traced - cumulative[i];
+        let elapsed = self.samples[i].offset - self.samples[0].offset;
+
+        (elapsed + self.elapsed_within(i, remaining)) / speed
+    }
+
+    /// Returns the number of seconds between the first and last samples.
+    fn span(&self) -> f64 {
+        match (self.samples.first(), self.samples.last()) {
+            (Some(first), Some(last)) => last.offset - first.offset,
+            _ => 0.0,
+        }
+    }
+
+    /// Returns how many seconds of the trace are replayed for each second of the replay.
+    fn speed(&self) -> f64 {
+        match self.duration {
+            Some(duration) => self.span() / duration.as_secs_f64(),
+            None => 1.0,
+        }
+    }
+
+    /// Returns the index of the interval between samples that starts before the sample at
+    /// the partition point.
+    fn interval(&self, partition: usize) -> usize {
+        partition.saturating_sub(1).min(self.samples.len() - 2)
+    }
+
+    /// Returns the number of requests sent by the trace (in its own time) up to each sample.
+    fn requests(&self) -> &[f64] {
+        self.requests.get_or_init(|| {
+            let mut total = 0.0;
+            let mut requests = vec![total];
+            for i in 0..self.samples.len() - 1 {
+                let length = self.samples[i + 1].offset - self.samples[i].offset;
+                total += self.traced_within(i, length);
+                requests.push(total);
+            }
+
+            requests
+        })
+    }
+
+    /// Calculates the number of requests sent by the trace `elapsed` seconds into the
+    /// interval that starts at the sample.
+    fn traced_within(&self, i: usize, elapsed: f64) -> f64 {
+        let (start, end) = (self.samples[i], self.samples[i + 1]);
+        match self.interpolation {
+            Interpolation::Step => start.rate * elapsed,
+            Interpolation::Linear => {
+                let slope = (end.rate - start.rate) / (end.offset - start.offset);
+                start.rate * elapsed + slope * elapsed * elapsed / 2.0
+            }
+        }
+    }
+
+    /// Calculates how many seconds into the interval that starts at the sample the given
+    /// number of requests should have been sent by the trace.
+    fn elapsed_within(&self, i: usize, requests: f64) -> f64 {
+        if requests <= 0.0 {
+            return 0.0;
+        }
+
+        let (start, end) = (self.samples[i], self.samples[i + 1]);
+        match self.interpolation {
+            Interpolation::Step => requests / start.rate,
+            Interpolation::Linear => {
+                let slope = (end.rate - start.rate) / (end.offset - start.offset);
+                let discriminant = (start.rate * start.rate + 2.0 * slope * requests).max(0.0);
+                2.0 * requests / (start.rate + discriminant.sqrt())
+            }
+        }
+    }
+
+    fn infer_format(path: &Path) -> Result<Format> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("csv") => Ok(Format::Csv),
+            Some("json") => Ok(Format::Json),
+            _ => bail!(
+                "Could not infer the format of trace file {} from its extension",
+                path.display()
+            ),
+        }
+    }
+
+    /// Reads `(timestamp, rate)` pairs from a CSV file.
+    ///
+    /// If the first row is a header, the `timestamp` and `rps` columns are used (falling
+    /// back to the first two columns if they are not present).
+    fn read_csv(file: File) -> Result<Vec<(f64, f64)>> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .trim(csv::Trim::All)
+            .from_reader(file);
+
+        let mut columns = (0, 1);
+        let mut points = vec![];
+        for (i, record) in reader.records().enumerate() {
+            let record = record?;
+            let field = |column: usize| record.get(column).unwrap_or_default();
+
+            if i == 0 && field(1).parse::<f64>().is_err() {
+                let find = |name| record.iter().position(|f| f.eq_ignore_ascii_case(name));
+                columns = (find("timestamp").unwrap_or(0), find("rps").unwrap_or(1));
+                continue;
+            }
+
+            let timestamp = parse_timestamp(field(columns.0))
+                .with_context(|| format!("Invalid timestamp on row {}", i + 1))?;
+            let rate = field(columns.1)
+                .parse()
+                .with_context(|| format!("Invalid rate on row {}", i + 1))?;
+
+            points.push((timestamp, rate));
+        }
+
+        Ok(points)
+    }
+
+    /// Reads `(timestamp, rate)` pairs from a JSON file.
+    fn read_json(file: File) -> Result<Vec<(f64, f64)>> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Point {
+            Pair(Timestamp, f64),
+            Object { timestamp: Timestamp, rps: f64 },
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Timestamp {
+            Secs(f64),
+            Text(String),
+        }
+
+        let points: Vec<Point> = serde_json::from_reader(file)?;
+        points
+            .into_iter()
+            .enumerate()
+            .map(|(i, point)| {
+                let (timestamp, rate) = match point {
+                    Point::Pair(timestamp, rate) => (timestamp, rate),
+                    Point::Object { timestamp, rps } => (timestamp, rps),
+                };
+
+                let timestamp = match timestamp {
+                    Timestamp::Secs(secs) => secs,
+                    Timestamp::Text(text) => parse_timestamp(&text)
+                        .with_context(|| format!("Invalid timestamp at index {}", i))?,
+                };
+
+                Ok((timestamp, rate))
+            })
+            .collect()
+    }
+}
+
+/// Parses a timestamp given as a number of seconds (e.g. since the Unix epoch) or in RFC 3339
+/// format, returning the number of seconds since the Unix epoch.
+fn parse_timestamp(s: &str) -> Result<f64> {
+    if let Ok(secs) = s.parse() {
+        return Ok(secs);
+    }
+
+    let time = humantime::parse_rfc3339_weak(s)?;
+    let secs = match time.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(since) => since.as_secs_f64(),
+        Err(err) => -err.duration().as_secs_f64(),
+    };
+
+    Ok(secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Loads a trace from a temporary file with the extension and contents.
+    fn load(extension: &str, contents: &str) -> Trace {
+        let file = tempfile::Builder::new()
+            .suffix(extension)
+            .tempfile()
+            .unwrap();
+        std::fs::write(file.path(), contents).unwrap();
+
+        let mut trace = Trace::new(file.path().to_owned());
+        trace.load().unwrap();
+        trace.validate().unwrap();
+        trace
+    }
+
+    fn samples(trace: &Trace) -> Vec<(f64, f64)> {
+        trace.samples.iter().map(|s| (s.offset, s.rate)).collect()
+    }
+
+    #[test]
+    fn read_csv() {
+        let csv = "timestamp,rps\n2023-03-01T00:00:00Z,10\n2023-03-01T00:01:00Z,20.5\n";
+        let trace = load(".csv", csv);
+        assert_eq!(samples(&trace), [(0.0, 10.0), (60.0, 20.5)]);
+        assert_eq!(trace.duration(), Duration::from_secs(60));
+
+        let csv = "rps,timestamp\n10,1677628800\n20,1677628830\n";
+        let trace = load(".csv", csv);
+        assert_eq!(samples(&trace), [(0.0, 10.0), (30.0, 20.0)]);
+
+        let csv = "100, 10\n160, 30\n";
+        let trace = load(".csv", csv);
+        assert_eq!(samples(&trace), [(0.0, 10.0), (60.0, 30.0)]);
+    }
+
+    #[test]
+    fn read_json() {
+        let json = r#"[
+            {"timestamp": "2023-03-01T00:00:00Z", "rps": 10},
+            {"timestamp": "2023-03-01T00:00:30Z", "rps": 5}
+        ]"#;
+
+        let trace = load(".json", json);
+        assert_eq!(samples(&trace), [(0.0, 10.0), (30.0, 5.0)]);
+
+        let trace = load(".json", "[[0, 1], [10, 2], [20, 3]]");
+        assert_eq!(samples(&trace), [(0.0, 1.0), (10.0, 2.0), (20.0, 3.0)]);
+    }
+
+    #[test]
+    fn invalid_traces() {
+        let mut trace = Trace::new("trace.csv".into());
+        assert!(trace.validate().is_err());
+
+        trace.samples = vec![
+            Sample {
+                offset: 0.0,
+                rate: 1.0,
+            },
+            Sample {
+                offset: 0.0,
+                rate: 1.0,
+            },
+        ];
+        assert!(trace.validate().is_err());
+
+        trace.samples[1].offset = 1.0;
+        trace.validate().unwrap();
+
+        trace.samples[1].rate = -1.0;
+        assert!(trace.validate().is_err());
+
+        trace.samples[1].rate = 1.0;
+        trace.scale = 0.0;
+        assert!(trace.validate().is_err());
+
+        assert!(Trace::new("trace.txt".into()).load().is_err());
+    }
+
+    #[test]
+    fn interpolation() {
+        let mut trace = Trace::new("trace.csv".into());
+        trace.samples = [(0.0, 10.0), (10.0, 0.0), (20.0, 0.0), (30.0, 20.0)]
+            .map(|(offset, rate)| Sample { offset, rate })
+            .to_vec();
+
+        // The rate falls to zero, stays there and then climbs back up.
+        assert_eq!(trace.requests_until(10.0), 50.0);
+        assert_eq!(trace.requests_until(15.0), 50.0);
+        assert_eq!(trace.requests_until(30.0), 150.0);
+        assert_eq!(trace.time_of(50.0), 20.0);
+        assert!((trace.time_of(75.0) - 25.0).abs() < 1e-9);
+
+        // Each sample's rate is held until the next.
+        trace.interpolation = Interpolation::Step;
+        trace.requests = OnceLock::new();
+        assert_eq!(trace.requests_until(10.0), 100.0);
+        assert_eq!(trace.requests_until(30.0), 100.0);
+        assert_eq!(trace.time_of(50.0), 5.0);
+
+        // Replaying the trace in a third of the time at double the rate sends two thirds of
+        // the requests.
+        trace.duration = Some(Duration::from_secs(10));
+        trace.scale = 2.0;
+        assert_eq!(trace.duration(), Duration::from_secs(10));
+        assert!((trace.requests_until(10.0) - 200.0 / 3.0).abs() < 1e-9);
+        assert!((trace.time_of(100.0 / 3.0) - 5.0 / 3.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,203 @@
+use std::time::Duration;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Virtual user plan for closed-loop profiling.
+///
+/// The plan dictates how many virtual users should be active over the course of the test.
+/// Each virtual user sends a request, waits for the response and then (optionally) thinks
+/// before sending its next request.
+#[derive(Clone, Debug, Deserialize)]
+pub struct UserPlan {
+    /// Segments that define how the number of users varies over the plan.
+    segments: Vec<UserSegment>,
+}
+
+/// Describes how the number of virtual users should be treated over a given duration.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum UserSegment {
+    /// Number of users should be fixed over the given duration (or forever).
+    Fixed {
+        users: usize,
+        #[serde(default)]
+        #[serde(with = "humantime_serde")]
+        duration: Option<Duration>,
+    },
+
+    /// Number of users should vary linearly over the given duration.
+    Linear {
+        users_start: usize,
+        users_end: usize,
+        #[serde(with = "humantime_serde")]
+        duration: Duration,
+    },
+}
+
+impl UserPlan {
+    pub fn new(segments: &[UserSegment]) -> Self {
+        Self {
+            segments: segments.to_vec(),
+        }
+    }
+
+    /// Calculates the number of users that should be active `progress` into the plan.
+    ///
+    /// If the returned value is `None` then we have completed the plan.
+    pub fn users_at(&self, progress: Duration) -> Option<usize> {
+        let mut seg_start = Duration::from_secs(0);
+        for seg in &self.segments {
+            let seg_progress = progress - seg_start;
+            match *seg {
+                UserSegment::Fixed { users, duration } => match duration {
+                    Some(d) if seg_progress >= d => seg_start += d,
+                    _ => return Some(users),
+                },
+                UserSegment::Linear {
+                    users_start,
+                    users_end,
+                    duration,
+                } => {
+                    if seg_progress >= duration {
+                        seg_start += duration;
+                    } else {
+                        let factor = seg_progress.as_secs_f64() / duration.as_secs_f64();
+                        let users = users_start as f64
+                            + (users_end as f64 - users_start as f64) * factor;
+
+                        return Some(users.round() as usize);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Distribution of the time that a virtual user waits between receiving a response and
+/// sending its next request.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ThinkTime {
+    /// Users always think for the same duration.
+    Fixed {
+        #[serde(with = "humantime_serde")]
+        duration: Duration,
+    },
+
+    /// Users think for a duration that is uniformly distributed between `min` and `max`.
+    Uniform {
+        #[serde(with = "humantime_serde")]
+        min: Duration,
+        #[serde(with = "humantime_serde")]
+        max: Duration,
+    },
+
+    /// Users think for a duration that is exponentially distributed around `mean`.
+    Exponential {
+        #[serde(with = "humantime_serde")]
+        mean: Duration,
+    },
+}
+
+impl ThinkTime {
+    /// Samples the next think time from the distribution.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> Duration {
+        match *self {
+            ThinkTime::Fixed { duration } => duration,
+            ThinkTime::Uniform { min, max } => {
+                if min >= max {
+                    min
+                } else {
+                    rng.gen_range(min..=max)
+                }
+            }
+            ThinkTime::Exponential { mean } => {
+                let u: f64 = rng.gen_range(0.0..1.0);
+                mean.mul_f64(-(1.0 - u).ln())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn users_at_segment_boundaries() {
+        let secs = Duration::from_secs_f64;
+        let plan = UserPlan::new(&[
+            UserSegment::Linear {
+                users_start: 0,
+                users_end: 10,
+                duration: secs(10.0),
+            },
+            UserSegment::Fixed {
+                users: 5,
+                duration: Some(secs(5.0)),
+            },
+            UserSegment::Linear {
+                users_start: 10,
+                users_end: 20,
+                duration: secs(10.0),
+            },
+        ]);
+
+        assert_eq!(plan.users_at(secs(0.0)), Some(0));
+        assert_eq!(plan.users_at(secs(5.0)), Some(5));
+        assert_eq!(plan.users_at(secs(9.99)), Some(10));
+
+        // Each segment takes over as soon as the previous segment has completed.
+        assert_eq!(plan.users_at(secs(10.0)), Some(5));
+        assert_eq!(plan.users_at(secs(14.99)), Some(5));
+        assert_eq!(plan.users_at(secs(15.0)), Some(10));
+        assert_eq!(plan.users_at(secs(20.0)), Some(15));
+        assert_eq!(plan.users_at(secs(25.0)), None);
+
+        let forever = UserPlan::new(&[UserSegment::Fixed {
+            users: 3,
+            duration: None,
+        }]);
+        assert_eq!(forever.users_at(secs(1e6)), Some(3));
+    }
+
+    #[test]
+    fn think_time_distributions() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let millis = Duration::from_millis;
+        let sample_mean = |think: ThinkTime, rng: &mut StdRng| {
+            let samples = (0..10_000).map(|_| think.sample(rng)).collect::<Vec<_>>();
+            let total = samples.iter().sum::<Duration>();
+            (samples, total / 10_000)
+        };
+
+        let fixed = ThinkTime::Fixed {
+            duration: millis(5),
+        };
+        assert_eq!(fixed.sample(&mut rng), millis(5));
+
+        let uniform = ThinkTime::Uniform {
+            min: millis(100),
+            max: millis(300),
+        };
+        let (samples, average) = sample_mean(uniform, &mut rng);
+        let bounds = millis(100)..=millis(300);
+        assert!(samples.iter().all(|s| bounds.contains(s)));
+        assert!(average.abs_diff(millis(200)) < millis(5), "{:?}", average);
+
+        let degenerate = ThinkTime::Uniform {
+            min: millis(100),
+            max: millis(100),
+        };
+        assert_eq!(degenerate.sample(&mut rng), millis(100));
+
+        let exponential = ThinkTime::Exponential { mean: millis(100) };
+        let (_, average) = sample_mean(exponential, &mut rng);
+        assert!(average.abs_diff(millis(100)) < millis(5), "{:?}", average);
+    }
+}